@@ -1,11 +1,26 @@
+use clap::{Parser, ValueEnum};
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
 use raytracer::{
     camera::Camera,
+    color::TransferFunction,
+    gltf_import,
     grid::Grid,
     hittable::{Hittable, Sphere},
+    integrator::{AmbientOcclusion, AnyIntegrator, Depth, Normals, PathTracer, RussianRoulette},
     material::Material,
-    render::{color_hit_by, Renderer},
+    progress::{ProgressObserver, TileProgress},
+    render::{Progressive, Renderer},
+    scene::Scene,
+    scene_file::{self, Setup},
+    tile::TileOrder,
+    tonemap::ToneMap,
     vector::Vec3,
 };
+use std::error::Error;
+use std::fmt::Write;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::time::Duration;
 
 const NUM_ANTIALIAS_SAMPLES: usize = 200;
 const FILENAME: &str = "fractal15.png";
@@ -14,24 +29,353 @@ const IMG_SCALE: usize = 400;
 const WIDTH: usize = 2 * IMG_SCALE;
 const HEIGHT: usize = IMG_SCALE;
 
+/// Renders a scene with raytracing, and saves it as an image.
+#[derive(Parser)]
+struct Args {
+    /// Scene file to render (see the scenes/ directory), or a glTF file (.gltf or .glb) with a
+    /// camera. Defaults to a built-in demo scene.
+    #[arg(long)]
+    scene: Option<String>,
+    /// Image width in pixels. Overrides the scene file.
+    #[arg(long)]
+    width: Option<NonZeroUsize>,
+    /// Image height in pixels. Overrides the scene file.
+    #[arg(long)]
+    height: Option<NonZeroUsize>,
+    /// Number of samples per pixel, for antialiasing. Overrides the scene file.
+    #[arg(long)]
+    samples: Option<NonZeroUsize>,
+    /// Where to save the image. The extension picks the format, e.g. .png, or .exr and .hdr for
+    /// high dynamic range. Overrides the scene file.
+    #[arg(short, long)]
+    output: Option<String>,
+    /// Seed for the random number generator. The same scene and seed always give the same image.
+    /// Overrides the scene file.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// How to display radiance brighter than 1 in 8-bit images. Overrides the scene file.
+    #[arg(long, value_enum)]
+    tone_map: Option<ToneMapArg>,
+    /// How bright the image should be with the exposure tone map (1 by default). Needs
+    /// `--tone-map exposure`, or a scene file that already uses it.
+    #[arg(long)]
+    exposure: Option<f64>,
+    /// Encode 8-bit images with this gamma, instead of the standard sRGB curve.
+    /// Overrides the scene file.
+    #[arg(long)]
+    gamma: Option<f64>,
+    /// Render in passes of this many samples per pixel, saving the image after each pass (at most
+    /// once per --save-interval), so you can stop once it looks good enough.
+    /// Overrides the scene file.
+    #[arg(long)]
+    progressive: Option<NonZeroUsize>,
+    /// Minimum number of seconds between saves, when rendering progressively (10 by default).
+    /// Needs --progressive, or a scene file that renders progressively. Overrides the scene file.
+    #[arg(long)]
+    save_interval: Option<u64>,
+    /// Render in square tiles this many pixels wide. Overrides the scene file.
+    #[arg(long)]
+    tile_size: Option<NonZeroUsize>,
+    /// Which order to render the tiles in. Overrides the scene file.
+    #[arg(long, value_enum)]
+    tile_order: Option<TileOrderArg>,
+    /// What to render: a proper path traced image, or one property of the scene, for debugging.
+    /// Overrides the scene file.
+    #[arg(long, value_enum)]
+    integrator: Option<IntegratorArg>,
+    /// Stop following paths after they've bounced this many times. Overrides the scene file.
+    #[arg(long)]
+    max_depth: Option<usize>,
+    /// Randomly cut off paths which aren't carrying much light, once they've bounced this many
+    /// times. Faster, but noisier. Overrides the scene file.
+    #[arg(long)]
+    russian_roulette: Option<usize>,
+    /// Don't send rays straight at emissive objects, only find their light by bouncing into them.
+    /// Much noisier with small lights, but handy for checking the light sampling.
+    #[arg(long)]
+    no_light_sampling: bool,
+    /// How many threads to render with. Defaults to one per CPU.
+    #[arg(long)]
+    threads: Option<NonZeroUsize>,
+    /// Also write the render's metrics to this file, as JSON.
+    #[arg(long)]
+    metrics_json: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum IntegratorArg {
+    Path,
+    Normals,
+    Depth,
+    AmbientOcclusion,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TileOrderArg {
+    Scanline,
+    Spiral,
+    Hilbert,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ToneMapArg {
+    Clamp,
+    Reinhard,
+    Aces,
+    Exposure,
+}
+
 fn main() {
-    let camera = Camera {
-        lower_left_corner: Vec3::new(-2.0, -1.0, -1.0),
-        horizontal: Vec3::new(4.0, 0.0, 0.0),
-        vertical: Vec3::new(0.0, 2.0, 0.0),
-        origin: Vec3::zero(),
+    if let Err(err) = run(Args::parse()) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.get())
+            .build_global()?;
+    }
+
+    let Setup {
+        mut renderer,
+        mut integrator,
+        scene,
+        mut width,
+        mut height,
+    } = match &args.scene {
+        Some(path) if is_gltf(path) => gltf_setup(path)?,
+        Some(path) => scene_file::load(path)?,
+        None => demo(),
     };
+    if args.width.is_some() || args.height.is_some() {
+        width = args.width.map_or(width, NonZeroUsize::get);
+        height = args.height.map_or(height, NonZeroUsize::get);
+        renderer
+            .camera
+            .set_aspect_ratio(width as f64 / height as f64);
+    }
+    if let Some(samples) = args.samples {
+        renderer.samples = samples.get();
+    }
+    if let Some(seed) = args.seed {
+        renderer.seed = seed;
+    }
+    if let Some(tone_map) = args.tone_map {
+        renderer.tone_map = match tone_map {
+            ToneMapArg::Clamp => ToneMap::Clamp,
+            ToneMapArg::Reinhard => ToneMap::Reinhard,
+            ToneMapArg::Aces => ToneMap::AcesFilmic,
+            // Keep the scene file's exposure if it already has one.
+            ToneMapArg::Exposure => match renderer.tone_map {
+                ToneMap::Exposure(exposure) => ToneMap::Exposure(exposure),
+                _ => ToneMap::Exposure(1.0),
+            },
+        };
+    }
+    if let Some(exposure) = args.exposure {
+        if exposure.is_nan() || exposure <= 0.0 {
+            return Err("--exposure must be positive".into());
+        }
+        match &mut renderer.tone_map {
+            ToneMap::Exposure(current) => *current = exposure,
+            _ => return Err("--exposure needs the exposure tone map".into()),
+        }
+    }
+    if let Some(gamma) = args.gamma {
+        if gamma.is_nan() || gamma <= 0.0 {
+            return Err("--gamma must be positive".into());
+        }
+        renderer.transfer = TransferFunction::Gamma(gamma);
+    }
+    if let Some(samples_per_pass) = args.progressive {
+        // Keep the scene file's save interval if it already renders progressively.
+        let save_interval = renderer
+            .progressive
+            .map_or(Duration::from_secs(10), |progressive| {
+                progressive.save_interval
+            });
+        renderer.progressive = Some(Progressive {
+            samples_per_pass: samples_per_pass.get(),
+            save_interval,
+        });
+    }
+    if let Some(save_interval) = args.save_interval {
+        match &mut renderer.progressive {
+            Some(progressive) => progressive.save_interval = Duration::from_secs(save_interval),
+            None => return Err("--save-interval needs a progressive render".into()),
+        }
+    }
+    if let Some(tile_size) = args.tile_size {
+        renderer.tile_size = tile_size.get();
+    }
+    if let Some(tile_order) = args.tile_order {
+        renderer.tile_order = match tile_order {
+            TileOrderArg::Scanline => TileOrder::Scanline,
+            TileOrderArg::Spiral => TileOrder::Spiral,
+            TileOrderArg::Hilbert => TileOrder::Hilbert,
+        };
+    }
+    if let Some(kind) = args.integrator {
+        // Keep the scene file's settings if it already uses this kind of integrator.
+        let current = match integrator {
+            AnyIntegrator::Path(_) => IntegratorArg::Path,
+            AnyIntegrator::Normals(_) => IntegratorArg::Normals,
+            AnyIntegrator::Depth(_) => IntegratorArg::Depth,
+            AnyIntegrator::AmbientOcclusion(_) => IntegratorArg::AmbientOcclusion,
+        };
+        if kind != current {
+            integrator = match kind {
+                IntegratorArg::Path => AnyIntegrator::Path(PathTracer::default()),
+                IntegratorArg::Normals => AnyIntegrator::Normals(Normals),
+                IntegratorArg::Depth => AnyIntegrator::Depth(Depth { max_distance: 10.0 }),
+                IntegratorArg::AmbientOcclusion => {
+                    AnyIntegrator::AmbientOcclusion(AmbientOcclusion {
+                        samples: 16,
+                        distance: 1.0,
+                    })
+                }
+            };
+        }
+    }
+    if args.max_depth.is_some() || args.russian_roulette.is_some() || args.no_light_sampling {
+        let path_tracer = match &mut integrator {
+            AnyIntegrator::Path(path_tracer) => path_tracer,
+            _ => return Err(
+                "--max-depth, --russian-roulette and --no-light-sampling need the path integrator"
+                    .into(),
+            ),
+        };
+        if let Some(max_depth) = args.max_depth {
+            path_tracer.path.max_depth = max_depth;
+        }
+        if let Some(start_depth) = args.russian_roulette {
+            path_tracer.path.russian_roulette = Some(RussianRoulette { start_depth });
+        }
+        if args.no_light_sampling {
+            path_tracer.sample_lights = false;
+        }
+    }
+    if let Some(output) = &args.output {
+        let output = Path::new(output);
+        let filename = output
+            .file_name()
+            .ok_or_else(|| format!("output path {} isn't a file", output.display()))?;
+        renderer.filename = filename.to_string_lossy().into_owned();
+        renderer.output_dir = output
+            .parent()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+
+    let pixels = Grid::new(width, height);
+    let metrics =
+        renderer.render_img_with_progress(scene, &integrator, pixels, &TerminalProgress::new())?;
+    eprintln!("{}", metrics.describe());
+    if let Some(path) = &args.metrics_json {
+        std::fs::write(path, metrics.to_json())
+            .map_err(|err| format!("couldn't write metrics to {}: {}", path, err))?;
+    }
+    Ok(())
+}
+
+/// Draws a progress bar on stderr, with the render's ETA and speed.
+struct TerminalProgress {
+    bar: ProgressBar,
+}
+
+impl TerminalProgress {
+    fn new() -> Self {
+        let style = ProgressStyle::with_template(
+            "[{elapsed_precise}] {wide_bar} {percent}% ({samples_per_sec}, ETA {eta})",
+        )
+        .unwrap()
+        .with_key(
+            "samples_per_sec",
+            |state: &ProgressState, w: &mut dyn Write| {
+                write!(w, "{:.2}M samples/sec", state.per_sec() / 1e6).unwrap()
+            },
+        );
+        Self {
+            bar: ProgressBar::new(0).with_style(style),
+        }
+    }
+}
+
+impl ProgressObserver for TerminalProgress {
+    fn started(&self, _total_tiles: usize, total_samples: usize) {
+        self.bar.set_length(total_samples as u64);
+    }
 
-    let r = Renderer {
-        output_dir: OUTPUT_DIR,
-        filename: FILENAME,
+    fn tile_done(&self, progress: &TileProgress) {
+        self.bar
+            .inc((progress.tile.size() * progress.samples) as u64);
+    }
+
+    fn finished(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+fn is_gltf(path: &str) -> bool {
+    let extension = Path::new(path).extension().and_then(|ext| ext.to_str());
+    matches!(extension, Some("gltf") | Some("glb"))
+}
+
+/// Render a glTF file straight from its own camera, with the default settings. The image is as
+/// wide as the demo's, and as tall as the camera's aspect ratio says.
+fn gltf_setup(path: &str) -> Result<Setup, Box<dyn Error>> {
+    let gltf = gltf_import::load(path, None)?;
+    let camera = gltf
+        .camera
+        .ok_or_else(|| format!("{} has no camera, so it needs a scene file", path))?;
+    let aspect_ratio = camera.aspect_ratio.unwrap_or(WIDTH as f64 / HEIGHT as f64);
+    let height = ((WIDTH as f64 / aspect_ratio).round() as usize).max(1);
+    let filename = Path::new(path).with_extension("png");
+    let mut renderer = default_renderer(camera.build(aspect_ratio));
+    renderer.filename = filename.file_name().unwrap().to_string_lossy().into_owned();
+    Ok(Setup {
+        renderer,
+        integrator: AnyIntegrator::Path(PathTracer::default()),
+        scene: Scene::with_lights(gltf.world, gltf.lights),
+        width: WIDTH,
+        height,
+    })
+}
+
+fn default_renderer(camera: Camera) -> Renderer {
+    Renderer {
+        output_dir: OUTPUT_DIR.to_owned(),
+        filename: FILENAME.to_owned(),
         camera,
         samples: NUM_ANTIALIAS_SAMPLES,
-    };
-    let pixels: Grid<[u8; 3], WIDTH, HEIGHT> = Default::default();
-    let metrics = r.render_img(scene(), color_hit_by, pixels);
-    eprintln!("{}", metrics.describe());
-    eprintln!("{:?}", metrics);
+        seed: 0,
+        tone_map: ToneMap::default(),
+        transfer: TransferFunction::default(),
+        progressive: None,
+        tile_size: 32,
+        tile_order: TileOrder::default(),
+    }
+}
+
+/// The scene to render when no scene file is given.
+fn demo() -> Setup {
+    let camera = Camera::new(
+        Vec3::zero(),
+        Vec3::new(0.0, 0.0, -1.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        90.0,
+        WIDTH as f64 / HEIGHT as f64,
+    );
+    Setup {
+        renderer: default_renderer(camera),
+        integrator: AnyIntegrator::Path(PathTracer::default()),
+        scene: Scene::new(scene()),
+        width: WIDTH,
+        height: HEIGHT,
+    }
 }
 
 fn scene() -> Hittable {
@@ -1,11 +1,60 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use raytracer::material::random_point_in_unit_sphere;
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use raytracer::hittable::{Hittable, Sphere};
+use raytracer::material::{random_point_in_unit_sphere, Material};
+use raytracer::ray::Ray;
+use raytracer::vector::Vec3;
 
 pub fn random_unit_sphere_benchmark(c: &mut Criterion) {
     c.bench_function("random_unit_sphere", |b| {
-        b.iter(|| random_point_in_unit_sphere())
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(0);
+        b.iter(|| random_point_in_unit_sphere(&mut rng))
     });
 }
 
-criterion_group!(benches, random_unit_sphere_benchmark);
+/// A 10x10x10 lattice of small spheres.
+fn lots_of_spheres() -> Vec<Hittable> {
+    let mut spheres = Vec::new();
+    for x in 0..10 {
+        for y in 0..10 {
+            for z in 0..10 {
+                spheres.push(Hittable::Sphere(Sphere {
+                    center: Vec3::new(x as f64, y as f64, -(z as f64) - 5.0),
+                    radius: 0.3,
+                    material: Material::Diffuse {
+                        albedo: Vec3::new_uniform(0.5),
+                    },
+                }));
+            }
+        }
+    }
+    spheres
+}
+
+pub fn bvh_benchmark(c: &mut Criterion) {
+    let flat = Hittable::Many(lots_of_spheres());
+    let bvh = Hittable::bvh(lots_of_spheres());
+    // Rays fanning out from in front of the lattice, some hitting and some missing.
+    let rays: Vec<Ray> = (0..100)
+        .map(|i| Ray {
+            origin: Vec3::new(4.5, 4.5, 0.0),
+            direction: Vec3::new((i % 10) as f64 - 5.0, (i / 10) as f64 - 5.0, -10.0),
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("hit_1000_spheres");
+    for (name, scene) in [("many", &flat), ("bvh", &bvh)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                rays.iter()
+                    .filter(|ray| scene.hit(ray, 0.001, f64::MAX).is_some())
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, random_unit_sphere_benchmark, bvh_benchmark);
 criterion_main!(benches);
@@ -0,0 +1,143 @@
+use crate::hittable::{Hit, Hittable};
+use crate::metrics;
+use crate::ray::Ray;
+use crate::vector::Vec3;
+use std::cmp::Ordering;
+
+/// Axis-aligned bounding box, i.e. a box whose edges are parallel to the x, y and z axes.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Does the ray pass through this box anywhere between t_min and t_max?
+    pub fn hit(&self, ray: &Ray, mut t_min: f64, mut t_max: f64) -> bool {
+        metrics::record_bounding_box_test();
+        // Check where the ray enters and leaves the "slab" between the box's two faces on each axis.
+        // If the ray is inside all three slabs at once, it's inside the box.
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// The smallest box which contains both boxes.
+    pub fn surrounding(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Vec3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Which axis (0 for x, 1 for y, 2 for z) is the box longest along?
+    pub(crate) fn longest_axis(&self) -> usize {
+        let size = self.max - self.min;
+        if size.x > size.y && size.x > size.z {
+            0
+        } else if size.y > size.z {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+/// A node in a bounding volume hierarchy: a binary tree where each node's bounding box contains
+/// all of its children. Rays which miss a node's box can skip everything underneath it, so finding
+/// the closest hit takes roughly logarithmic time instead of checking every object.
+pub struct BvhNode {
+    bbox: Aabb,
+    left: Box<Hittable>,
+    /// Leaves which only hold one object don't have a right child.
+    right: Option<Box<Hittable>>,
+}
+
+impl BvhNode {
+    /// Build a tree over the given objects, which must not be empty.
+    /// Each level splits its objects in half at the median along the longest axis.
+    pub(crate) fn build(mut objects: Vec<(Aabb, Hittable)>) -> Self {
+        let bbox = objects
+            .iter()
+            .map(|(bbox, _)| *bbox)
+            .reduce(|a, b| a.surrounding(&b))
+            .expect("can't build a BVH with no objects");
+
+        if objects.len() <= 2 {
+            let mut objects = objects.into_iter().map(|(_, object)| Box::new(object));
+            return BvhNode {
+                bbox,
+                left: objects.next().unwrap(),
+                right: objects.next(),
+            };
+        }
+
+        // Split along whichever axis the objects' centers are most spread out on.
+        let centroid_bounds = objects
+            .iter()
+            .map(|(bbox, _)| {
+                let c = bbox.centroid();
+                Aabb { min: c, max: c }
+            })
+            .reduce(|a, b| a.surrounding(&b))
+            .unwrap();
+        let axis = centroid_bounds.longest_axis();
+        let mid = objects.len() / 2;
+        objects.select_nth_unstable_by(mid, |(a, _), (b, _)| {
+            a.centroid()[axis]
+                .partial_cmp(&b.centroid()[axis])
+                .unwrap_or(Ordering::Equal)
+        });
+        let right = objects.split_off(mid);
+        BvhNode {
+            bbox,
+            left: Box::new(Hittable::Bvh(BvhNode::build(objects))),
+            right: Some(Box::new(Hittable::Bvh(BvhNode::build(right)))),
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    pub(crate) fn children(&self) -> impl Iterator<Item = &Hittable> {
+        std::iter::once(&*self.left).chain(self.right.as_deref())
+    }
+
+    pub(crate) fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        if !self.bbox.hit(ray, t_min, t_max) {
+            return None;
+        }
+        let left = self.left.hit(ray, t_min, t_max);
+        // The right child only matters if it's hit closer than the left child was.
+        let t_max = left.as_ref().map_or(t_max, |hit| hit.t);
+        let right = self
+            .right
+            .as_ref()
+            .and_then(|right| right.hit(ray, t_min, t_max));
+        right.or(left)
+    }
+}
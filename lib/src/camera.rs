@@ -1,5 +1,7 @@
 use crate::ray::Ray;
 use crate::vector::Vec3;
+use rand::Rng;
+use rand_xoshiro::Xoshiro256PlusPlus;
 
 #[derive(Clone, Copy)]
 pub struct Camera {
@@ -7,16 +9,99 @@ pub struct Camera {
     pub horizontal: Vec3,
     pub vertical: Vec3,
     pub origin: Vec3,
+    /// Unit vector pointing to the camera's right.
+    pub right: Vec3,
+    /// Unit vector pointing up, from the camera's point of view.
+    pub up: Vec3,
+    /// Rays start from random points on a lens this big, which blurs anything that isn't in focus.
+    /// Zero means everything is in perfect focus, like a pinhole camera.
+    pub lens_radius: f64,
 }
 
 impl Camera {
+    /// Position a camera at `look_from`, pointing towards `look_at`.
+    /// `view_up` says which way is "up" for the camera, i.e. it controls how the camera is rolled.
+    /// `vfov` is the vertical field of view in degrees, and `aspect_ratio` is width / height.
+    /// Everything will be in focus; see `Camera::with_depth_of_field` for a more realistic lens.
+    pub fn new(
+        look_from: Vec3,
+        look_at: Vec3,
+        view_up: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+    ) -> Self {
+        Self::with_depth_of_field(look_from, look_at, view_up, vfov, aspect_ratio, 0.0, 1.0)
+    }
+
+    /// Like `Camera::new`, but with a lens `aperture` units wide, focused on objects
+    /// `focus_dist` units away from the camera. Objects nearer or further will be blurry.
+    pub fn with_depth_of_field(
+        look_from: Vec3,
+        look_at: Vec3,
+        view_up: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
+    ) -> Self {
+        let half_height = (vfov.to_radians() / 2.0).tan();
+        let half_width = aspect_ratio * half_height;
+
+        // Build an orthonormal basis for the camera: w points backwards (away from whatever
+        // the camera's looking at), u points to the camera's right, and v points up.
+        let w = (look_from - look_at).unit();
+        let u = view_up.cross(w).unit();
+        let v = w.cross(u);
+
+        // The image plane sits at the focus distance, so that's where rays from every part of the
+        // lens converge.
+        Camera {
+            lower_left_corner: look_from
+                - u * (half_width * focus_dist)
+                - v * (half_height * focus_dist)
+                - w * focus_dist,
+            horizontal: u * (2.0 * half_width * focus_dist),
+            vertical: v * (2.0 * half_height * focus_dist),
+            origin: look_from,
+            right: u,
+            up: v,
+            lens_radius: aperture / 2.0,
+        }
+    }
+
+    /// Stretch or squash the image horizontally, so it has the given aspect ratio (width / height).
+    /// The vertical field of view stays the same.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) {
+        let center = self.lower_left_corner + self.horizontal * 0.5 + self.vertical * 0.5;
+        self.horizontal = self.right * (self.vertical.length() * aspect_ratio);
+        self.lower_left_corner = center - self.horizontal * 0.5 - self.vertical * 0.5;
+    }
+
     /// Get the ray which starts at the camera, crosses through the image, and hits the given point.
-    pub fn ray_to_point(&self, u: f64, v: f64) -> Ray {
-        let direction =
-            self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin;
-        Ray {
-            origin: self.origin,
-            direction,
+    pub fn ray_to_point(&self, u: f64, v: f64, rng: &mut Xoshiro256PlusPlus) -> Ray {
+        // Start the ray from a random point on the lens, instead of always from its center.
+        let lens_offset = if self.lens_radius > 0.0 {
+            let p = random_point_in_unit_disk(rng) * self.lens_radius;
+            self.right * p.x + self.up * p.y
+        } else {
+            Vec3::zero()
+        };
+        let origin = self.origin + lens_offset;
+        let direction = self.lower_left_corner + self.horizontal * u + self.vertical * v - origin;
+        Ray { origin, direction }
+    }
+}
+
+/// Picks a random point inside the circle of radius 1 on the xy plane.
+fn random_point_in_unit_disk(rng: &mut Xoshiro256PlusPlus) -> Vec3 {
+    loop {
+        let p = Vec3::new(
+            rng.gen::<f64>() * 2.0 - 1.0,
+            rng.gen::<f64>() * 2.0 - 1.0,
+            0.0,
+        );
+        if p.squared_length() < 1.0 {
+            return p;
         }
     }
 }
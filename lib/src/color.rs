@@ -5,6 +5,58 @@ const RGB_CORRECTION: f64 = 255.9999;
 #[derive(Clone, Copy)]
 pub struct Color(pub Vec3);
 
+/// Image files and displays don't store light linearly, they spend more of their precision on dark
+/// shades, because that's where eyes are most sensitive. Transfer functions convert between
+/// linear light (which the renderer works with) and these encoded values.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum TransferFunction {
+    /// The piecewise sRGB curve, which almost every 8-bit image and monitor uses.
+    #[default]
+    Srgb,
+    /// A plain power curve: encoded = linear ^ (1 / gamma).
+    Gamma(f64),
+    /// No encoding at all.
+    Linear,
+}
+
+impl TransferFunction {
+    /// Convert a linear value between 0 and 1 to its encoded value.
+    pub fn encode(&self, linear: f64) -> f64 {
+        match self {
+            Self::Srgb => srgb_encode(linear),
+            Self::Gamma(gamma) => linear.powf(1.0 / gamma),
+            Self::Linear => linear,
+        }
+    }
+
+    /// Convert an encoded value between 0 and 1 (e.g. from a texture) back to linear.
+    pub fn decode(&self, encoded: f64) -> f64 {
+        match self {
+            Self::Srgb => srgb_decode(encoded),
+            Self::Gamma(gamma) => encoded.powf(*gamma),
+            Self::Linear => encoded,
+        }
+    }
+}
+
+/// The sRGB transfer function: linear near black, then roughly gamma 2.4.
+pub fn srgb_encode(linear: f64) -> f64 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The inverse of `srgb_encode`.
+pub fn srgb_decode(encoded: f64) -> f64 {
+    if encoded <= 0.040_45 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
 fn assert_is_probability(f: f64) {
     assert!(f >= 0.0);
     assert!(f <= 1.0);
@@ -27,6 +79,13 @@ impl Color {
         Color(Vec3 { x: r, y: g, z: b })
     }
 
+    /// Like `Color::from`, but components outside of [0, 1] are clamped instead of panicking.
+    /// NaN components become 0.
+    pub fn clamped(v: Vec3) -> Self {
+        let clamp = |c: f64| if c.is_nan() { 0.0 } else { c.clamp(0.0, 1.0) };
+        Color::new(clamp(v.x), clamp(v.y), clamp(v.z))
+    }
+
     pub fn new_uniform(f: f64) -> Self {
         Color::new(f, f, f)
     }
@@ -59,11 +118,18 @@ impl Color {
         ]
     }
 
+    /// Like `to_rgb`, but with gamma 2.0. Prefer `to_rgb_encoded` with sRGB, which is what
+    /// displays actually expect.
     pub fn to_rgb_gamma_corrected(&self) -> [u8; 3] {
+        self.to_rgb_encoded(TransferFunction::Gamma(2.0))
+    }
+
+    /// Converts to the standard RGB format, encoding each channel with the transfer function.
+    pub fn to_rgb_encoded(&self, transfer: TransferFunction) -> [u8; 3] {
         [
-            (self.r().sqrt() * RGB_CORRECTION) as u8,
-            (self.g().sqrt() * RGB_CORRECTION) as u8,
-            (self.b().sqrt() * RGB_CORRECTION) as u8,
+            (transfer.encode(self.r()) * RGB_CORRECTION) as u8,
+            (transfer.encode(self.g()) * RGB_CORRECTION) as u8,
+            (transfer.encode(self.b()) * RGB_CORRECTION) as u8,
         ]
     }
 
@@ -0,0 +1,77 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Everything that can go wrong while rendering an image.
+#[derive(Debug)]
+pub enum RenderError {
+    /// Couldn't write the image to the filesystem.
+    Io(std::io::Error),
+    /// The output filename's extension isn't an image format we know how to write.
+    UnsupportedExtension(String),
+    /// The directory the image should be saved in doesn't exist.
+    MissingOutputDir(PathBuf),
+    /// A setting doesn't make sense, e.g. rendering with zero samples per pixel.
+    InvalidParameter(String),
+    /// The image couldn't be encoded.
+    Image(image::ImageError),
+    /// The OpenEXR image couldn't be encoded.
+    Exr(exr::error::Error),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "couldn't write image: {}", err),
+            Self::UnsupportedExtension(filename) => {
+                write!(
+                    f,
+                    "can't write {}, it isn't a supported image format",
+                    filename
+                )
+            }
+            Self::MissingOutputDir(dir) => {
+                write!(f, "can't write image, {} doesn't exist", dir.display())
+            }
+            Self::InvalidParameter(reason) => write!(f, "invalid parameter: {}", reason),
+            Self::Image(err) => write!(f, "couldn't encode image: {}", err),
+            Self::Exr(err) => write!(f, "couldn't encode OpenEXR image: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Image(err) => Some(err),
+            Self::Exr(err) => Some(err),
+            Self::UnsupportedExtension(_)
+            | Self::MissingOutputDir(_)
+            | Self::InvalidParameter(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RenderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<image::ImageError> for RenderError {
+    fn from(err: image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(err) => Self::Io(err),
+            err => Self::Image(err),
+        }
+    }
+}
+
+impl From<exr::error::Error> for RenderError {
+    fn from(err: exr::error::Error) -> Self {
+        match err {
+            exr::error::Error::Io(err) => Self::Io(err),
+            err => Self::Exr(err),
+        }
+    }
+}
@@ -0,0 +1,390 @@
+//! Loads scenes from glTF 2.0 files (both `.gltf` and binary `.glb`), which is what Blender and
+//! most other tools export for whole scenes. Every mesh in the file's default scene is placed
+//! where its node puts it, and the file's cameras and lights (from `KHR_lights_punctual`) come
+//! along too.
+//!
+//! Materials use the metallic-roughness model, and get mapped onto the closest `Material`:
+//! - anything with an emissive color becomes `Emissive`, scaled by
+//!   `KHR_materials_emissive_strength`
+//! - transmissive materials (`KHR_materials_transmission`) become `Dielectric`, using
+//!   `KHR_materials_ior`
+//! - metallic materials become `Metal`, colored by the base color, blurred by the roughness
+//! - everything else becomes `Diffuse`, colored by the base color
+//!
+//! Textures, orthographic cameras, animations and skins are skipped.
+use crate::camera::Camera;
+use crate::hittable::Hittable;
+use crate::light::Light;
+use crate::material::Material;
+use crate::mesh::{Mesh, MeshData};
+use crate::obj::DEFAULT_MATERIAL;
+use crate::vector::Vec3;
+use gltf::khr_lights_punctual::Kind;
+use gltf::mesh::Mode;
+use gltf::Node;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Everything imported from a glTF file.
+pub struct GltfScene {
+    /// All the file's meshes.
+    pub world: Hittable,
+    /// The file's lights. Their intensities are used as they are, even though glTF measures them
+    /// in candela (or lux, for directional lights), so they might need adjusting.
+    pub lights: Vec<Light>,
+    /// The first perspective camera in the scene, if there is one.
+    pub camera: Option<GltfCamera>,
+}
+
+/// Where a glTF camera is and what it can see. Doesn't become a `Camera` straight away, because
+/// glTF cameras don't always say what aspect ratio they want.
+#[derive(Clone, Copy, Debug)]
+pub struct GltfCamera {
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    pub view_up: Vec3,
+    /// Vertical field of view, in degrees.
+    pub vfov: f64,
+    /// Width / height, if the file gives one. Only a hint for how big to make the image: the
+    /// camera always matches the image it renders, or the picture would come out stretched.
+    pub aspect_ratio: Option<f64>,
+}
+
+impl GltfCamera {
+    /// Make the camera, for an image with the given aspect ratio (width / height).
+    pub fn build(&self, aspect_ratio: f64) -> Camera {
+        Camera::new(
+            self.look_from,
+            self.look_at,
+            self.view_up,
+            self.vfov,
+            aspect_ratio,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum GltfError {
+    /// The file couldn't be read or parsed, or its buffers couldn't be loaded.
+    Load(PathBuf, gltf::Error),
+    /// The file parsed, but describes something we can't render, e.g. a triangle whose vertices
+    /// don't exist.
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Load(path, err) => write!(f, "couldn't load {}: {}", path.display(), err),
+            Self::Invalid(path, reason) => write!(f, "{}: {}", path.display(), reason),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Load(_, err) => Some(err),
+            Self::Invalid(..) => None,
+        }
+    }
+}
+
+/// Read the glTF file at the given path, and any buffers it uses (which are looked for next to
+/// it). Each mesh primitive becomes a separate mesh. If `material` is given, every mesh uses it
+/// instead of the file's materials.
+pub fn load<P: AsRef<Path>>(path: P, material: Option<Material>) -> Result<GltfScene, GltfError> {
+    let path = path.as_ref();
+    let load_error = |err| GltfError::Load(path.to_owned(), err);
+    let gltf::Gltf { document, blob } = gltf::Gltf::open(path).map_err(load_error)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    // Only the buffers are needed, so don't bother decoding any images.
+    let buffers = gltf::import_buffers(&document, Some(dir), blob).map_err(load_error)?;
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| GltfError::Invalid(path.to_owned(), "file has no scenes".to_owned()))?;
+    let mut importer = Importer {
+        buffers: &buffers,
+        material,
+        objects: Vec::new(),
+        lights: Vec::new(),
+        camera: None,
+    };
+    for node in scene.nodes() {
+        importer
+            .add_node(&node, &Transform::IDENTITY)
+            .map_err(|reason| GltfError::Invalid(path.to_owned(), reason))?;
+    }
+    Ok(GltfScene {
+        world: Hittable::bvh(importer.objects),
+        lights: importer.lights,
+        camera: importer.camera,
+    })
+}
+
+/// Collects everything in a scene while walking through its nodes.
+struct Importer<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    material: Option<Material>,
+    objects: Vec<Hittable>,
+    lights: Vec<Light>,
+    camera: Option<GltfCamera>,
+}
+
+impl Importer<'_> {
+    /// Add the node and all its children. `parent` places the node's parent in the scene.
+    fn add_node(&mut self, node: &Node, parent: &Transform) -> Result<(), String> {
+        let transform = parent.then(&Transform::of_node(node));
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                let object = self
+                    .primitive_mesh(&primitive, &transform)
+                    .map_err(|reason| {
+                        format!(
+                            "mesh {}, primitive {}: {}",
+                            mesh.index(),
+                            primitive.index(),
+                            reason
+                        )
+                    })?;
+                self.objects.extend(object.map(Hittable::Mesh));
+            }
+        }
+        if let Some(camera) = node.camera() {
+            if self.camera.is_none() {
+                self.camera = perspective_camera(&camera, &transform)
+                    .map_err(|reason| format!("camera {}: {}", camera.index(), reason))?;
+            }
+        }
+        if let Some(light) = node.light() {
+            self.lights.push(punctual_light(&light, &transform));
+        }
+        for child in node.children() {
+            self.add_node(&child, &transform)?;
+        }
+        Ok(())
+    }
+
+    /// Bake the primitive's vertices into the scene's space, so the mesh needs no transform of
+    /// its own. None if the primitive isn't made of triangles (e.g. it's lines or points).
+    fn primitive_mesh(
+        &self,
+        primitive: &gltf::Primitive,
+        transform: &Transform,
+    ) -> Result<Option<Mesh>, String> {
+        let reader = primitive.reader(|buffer| self.buffers.get(buffer.index()).map(|b| &b.0[..]));
+        let positions: Vec<_> = match reader.read_positions() {
+            Some(positions) => positions.map(|p| transform.point(vec3(p))).collect(),
+            None => return Ok(None),
+        };
+        let normals = reader
+            .read_normals()
+            .map(|normals| normals.map(|n| transform.normal(vec3(n))).collect());
+        let uvs = reader.read_tex_coords(0).map(|uvs| {
+            uvs.into_f32()
+                .map(|[u, v]| (f64::from(u), f64::from(v)))
+                .collect()
+        });
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let mut triangles: Vec<[usize; 3]> = match primitive.mode() {
+            Mode::Triangles => indices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect(),
+            // Every other triangle in a strip goes the other way round, so flip those back.
+            Mode::TriangleStrip => indices
+                .windows(3)
+                .enumerate()
+                .map(|(i, t)| {
+                    if i % 2 == 0 {
+                        [t[0], t[1], t[2]]
+                    } else {
+                        [t[0], t[2], t[1]]
+                    }
+                })
+                .collect(),
+            Mode::TriangleFan => indices
+                .windows(2)
+                .skip(1)
+                .map(|t| [t[0], t[1], indices[0]])
+                .collect(),
+            Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => return Ok(None),
+        };
+        // Mirroring the mesh turns its triangles inside out, so turn them back.
+        if transform.determinant() < 0.0 {
+            for triangle in &mut triangles {
+                triangle.swap(1, 2);
+            }
+        }
+
+        let data = MeshData {
+            positions,
+            normals,
+            uvs,
+            triangles,
+        };
+        let material = self
+            .material
+            .unwrap_or_else(|| material(&primitive.material()));
+        let mesh = Mesh::new(Arc::new(data), material).map_err(|err| err.to_string())?;
+        Ok(Some(mesh))
+    }
+}
+
+/// The closest `Material` to a glTF material.
+fn material(material: &gltf::Material) -> Material {
+    // Primitives without a material get glTF's default, which is a white metal. A plain diffuse
+    // is much more like what modelling tools show.
+    if material.index().is_none() {
+        return DEFAULT_MATERIAL;
+    }
+    let emission = material.emissive_strength().unwrap_or(1.0);
+    let radiance = vec3(material.emissive_factor()) * f64::from(emission);
+    if radiance.max_component() > 0.0 {
+        return Material::Emissive { radiance };
+    }
+    let transmission = material
+        .transmission()
+        .map_or(0.0, |transmission| transmission.transmission_factor());
+    if transmission >= 0.5 {
+        return Material::Dielectric {
+            refraction_index: f64::from(material.ior().unwrap_or(1.5)),
+        };
+    }
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _] = pbr.base_color_factor();
+    let albedo = vec3([r, g, b]);
+    if pbr.metallic_factor() >= 0.5 {
+        Material::Metal {
+            albedo,
+            fuzz: f64::from(pbr.roughness_factor()),
+        }
+    } else {
+        Material::Diffuse { albedo }
+    }
+}
+
+/// glTF cameras look down their node's -Z axis, with +Y pointing up. None for orthographic
+/// cameras, which we can't render.
+fn perspective_camera(
+    camera: &gltf::Camera,
+    transform: &Transform,
+) -> Result<Option<GltfCamera>, String> {
+    let perspective = match camera.projection() {
+        gltf::camera::Projection::Perspective(perspective) => perspective,
+        gltf::camera::Projection::Orthographic(_) => return Ok(None),
+    };
+    let vfov = f64::from(perspective.yfov()).to_degrees();
+    if !(vfov > 0.0 && vfov < 180.0) {
+        return Err(format!(
+            "field of view must be between 0 and 180 degrees, not {}",
+            vfov
+        ));
+    }
+    let look_from = transform.point(Vec3::zero());
+    Ok(Some(GltfCamera {
+        look_from,
+        look_at: look_from + transform.vector(Vec3::new(0.0, 0.0, -1.0)),
+        view_up: transform.vector(Vec3::new(0.0, 1.0, 0.0)),
+        vfov,
+        aspect_ratio: perspective.aspect_ratio().map(f64::from),
+    }))
+}
+
+/// glTF lights sit at their node's origin, and shine down its -Z axis.
+fn punctual_light(light: &gltf::khr_lights_punctual::Light, transform: &Transform) -> Light {
+    let intensity = vec3(light.color()) * f64::from(light.intensity());
+    let position = transform.point(Vec3::zero());
+    let direction = transform.vector(Vec3::new(0.0, 0.0, -1.0)).unit();
+    match light.kind() {
+        Kind::Directional => Light::Directional {
+            direction,
+            irradiance: intensity,
+        },
+        Kind::Point => Light::Point {
+            position,
+            intensity,
+        },
+        Kind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } => Light::Spot {
+            position,
+            direction,
+            intensity,
+            cone_angle: f64::from(outer_cone_angle).to_degrees(),
+            falloff_start: f64::from(inner_cone_angle).to_degrees(),
+        },
+    }
+}
+
+fn vec3([x, y, z]: [f32; 3]) -> Vec3 {
+    Vec3::new(f64::from(x), f64::from(y), f64::from(z))
+}
+
+/// Moves, rotates and scales things from a node's space into its parent's. A 4x4 matrix, stored
+/// column by column like glTF does.
+struct Transform([[f64; 4]; 4]);
+
+impl Transform {
+    const IDENTITY: Self = Transform([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    fn of_node(node: &Node) -> Self {
+        Transform(
+            node.transform()
+                .matrix()
+                .map(|column| column.map(f64::from)),
+        )
+    }
+
+    /// Apply `child` first, then this.
+    fn then(&self, child: &Transform) -> Transform {
+        let mut m = [[0.0; 4]; 4];
+        for (column, child_column) in m.iter_mut().zip(&child.0) {
+            for (row, value) in column.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.0[k][row] * child_column[k]).sum();
+            }
+        }
+        Transform(m)
+    }
+
+    /// One of the first three columns: where the X, Y or Z axis ends up, ignoring translation.
+    fn axis(&self, i: usize) -> Vec3 {
+        let [x, y, z, _] = self.0[i];
+        Vec3::new(x, y, z)
+    }
+
+    fn point(&self, p: Vec3) -> Vec3 {
+        let [x, y, z, _] = self.0[3];
+        self.vector(p) + Vec3::new(x, y, z)
+    }
+
+    fn vector(&self, v: Vec3) -> Vec3 {
+        self.axis(0) * v.x + self.axis(1) * v.y + self.axis(2) * v.z
+    }
+
+    /// Normals have to stay perpendicular to the surface, even if it's squashed or stretched, so
+    /// they're transformed by the inverse transpose. That's the same as the cofactor matrix (whose
+    /// columns are the cross products of the axes) divided by the determinant.
+    fn normal(&self, n: Vec3) -> Vec3 {
+        let [x, y, z] = [self.axis(0), self.axis(1), self.axis(2)];
+        let normal = y.cross(z) * n.x + z.cross(x) * n.y + x.cross(y) * n.z;
+        (normal * self.determinant().signum()).unit()
+    }
+
+    fn determinant(&self) -> f64 {
+        self.axis(0).dot(&self.axis(1).cross(self.axis(2)))
+    }
+}
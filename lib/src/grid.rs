@@ -1,30 +1,51 @@
+use crate::tile::Tile;
+use std::sync::Mutex;
+
 pub struct Point {
     pub x: usize,
     pub y: usize,
 }
 
-/// Just a two-dimensional array.
-pub struct Grid<T, const W: usize, const H: usize> {
-    array: [[T; W]; H],
+/// Just a two-dimensional array. It's stored on the heap, so its size can be chosen at runtime,
+/// and big images won't overflow the stack.
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    /// Row-major, i.e. each row is stored contiguously, one after the other.
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T>
+where
+    T: Default + Clone,
+{
+    /// Make a grid where every element has the default value.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Default::default(); width * height],
+        }
+    }
 }
 
-impl<T, const W: usize, const H: usize> Grid<T, W, H> {
+impl<T> Grid<T> {
     /// Get the element at the specified 2D location in the grid.
     pub fn get(&self, p: &Point) -> &T {
-        &self.array[p.y][p.x]
+        &self.cells[p.y * self.width + p.x]
     }
 
     /// How many elements are in the grid?
     pub fn size(&self) -> usize {
-        W * H
+        self.width * self.height
     }
 
     pub fn height(&self) -> usize {
-        H
+        self.height
     }
 
     pub fn width(&self) -> usize {
-        W
+        self.width
     }
 
     /// Recompute each item in the grid using the provided setter function.
@@ -34,23 +55,71 @@ impl<T, const W: usize, const H: usize> Grid<T, W, H> {
     where
         F: Send + Sync + Fn(Point) -> T,
         T: Send,
+    {
+        self.update_all_parallel(|p, item| *item = setter(p))
+    }
+
+    /// Like `set_all_parallel`, but the updater function can see each item's current value,
+    /// and modifies it in place.
+    pub fn update_all_parallel<F>(&mut self, updater: F)
+    where
+        F: Send + Sync + Fn(Point, &mut T),
+        T: Send,
     {
         use rayon::prelude::*;
-        self.array.par_iter_mut().enumerate().for_each(|(y, row)| {
-            for (x, item) in row.iter_mut().enumerate() {
-                *item = setter(Point { x, y });
-            }
-        })
+        // Chunks can't be empty, but if the grid has no columns, it has no cells to set anyway.
+        let width = self.width.max(1);
+        self.cells
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, item) in row.iter_mut().enumerate() {
+                    updater(Point { x, y }, item);
+                }
+            })
     }
-}
 
-impl<T, const W: usize, const H: usize> Default for Grid<T, W, H>
-where
-    T: Default + Copy,
-{
-    fn default() -> Self {
-        Self {
-            array: [[Default::default(); W]; H],
-        }
+    /// Like `update_all_parallel`, but works through the grid one tile at a time, starting tiles in
+    /// the order given. After each tile is finished, `on_tile_done` is called with the tile and
+    /// its updated items (row by row).
+    pub fn update_tiles_parallel<F, C>(&mut self, tiles: &[Tile], updater: F, on_tile_done: C)
+    where
+        F: Send + Sync + Fn(Point, &mut T),
+        C: Send + Sync + Fn(&Tile, &[T]),
+        T: Send + Clone,
+    {
+        use rayon::prelude::*;
+        let width = self.width;
+        let cells = Mutex::new(&mut self.cells);
+        let rows = |tile: Tile| {
+            (tile.y..tile.y + tile.height)
+                .map(move |y| y * width + tile.x..y * width + tile.x + tile.width)
+        };
+        // par_bridge hands out tiles in order, as threads become free, so that the first tiles
+        // really are rendered first.
+        tiles.iter().par_bridge().for_each(|tile| {
+            // Copy the tile out of the grid, so that it can be updated without holding the lock.
+            let mut items = Vec::with_capacity(tile.size());
+            {
+                let cells = cells.lock().unwrap();
+                for row in rows(*tile) {
+                    items.extend_from_slice(&cells[row]);
+                }
+            }
+            for (i, item) in items.iter_mut().enumerate() {
+                let p = Point {
+                    x: tile.x + i % tile.width,
+                    y: tile.y + i / tile.width,
+                };
+                updater(p, item);
+            }
+            {
+                let mut cells = cells.lock().unwrap();
+                for (row, updated) in rows(*tile).zip(items.chunks(tile.width)) {
+                    cells[row].clone_from_slice(updated);
+                }
+            }
+            on_tile_done(tile, &items);
+        })
     }
 }
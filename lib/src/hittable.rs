@@ -1,23 +1,118 @@
+use crate::bvh::{Aabb, BvhNode};
 use crate::material::Material;
+use crate::mesh::Mesh;
+use crate::metrics;
 use crate::ray::Ray;
+use crate::scene::Emitter;
 use crate::vector::Vec3;
+use std::f64::consts::PI;
 
 pub enum Hittable {
     Sphere(Sphere),
+    Triangle(Triangle),
+    Mesh(Mesh),
     Many(Vec<Hittable>),
+    /// Like `Many`, but organized so that rays only check the objects they might actually hit.
+    /// Build these with `Hittable::bvh`.
+    Bvh(BvhNode),
 }
 
 pub struct Hit {
     pub t: f64,
     pub p: Vec3,
+    /// Always points back towards the side the ray came from, so it can be used to bounce the ray
+    /// off either side of the surface.
     pub normal: Vec3,
+    /// Did the ray hit the outside of the surface (the side the outward normal points to)? False
+    /// if it hit the surface from behind, e.g. a ray leaving a glass sphere.
+    pub front_face: bool,
     pub material: Material,
+    pub(crate) uv: Uv,
+}
+
+/// Texture coordinates of a hit, or how to work them out.
+#[derive(Clone, Copy)]
+pub(crate) enum Uv {
+    Known(f64, f64),
+    /// Working out where on a sphere a point is takes some trigonometry, which is wasted on every
+    /// hit except the closest one. So it's left until someone asks.
+    Sphere,
+}
+
+impl Hit {
+    /// Texture coordinates of the point that was hit.
+    pub fn uv(&self) -> (f64, f64) {
+        match self.uv {
+            Uv::Known(u, v) => (u, v),
+            Uv::Sphere => {
+                let outward_normal = if self.front_face {
+                    self.normal
+                } else {
+                    -self.normal
+                };
+                sphere_uv(&outward_normal)
+            }
+        }
+    }
 }
 
 impl Hittable {
+    /// Organize the objects into a bounding volume hierarchy, which is much faster to hit than
+    /// `Hittable::Many` when there are lots of objects. Objects without a bounding box
+    /// (e.g. an empty `Many`) can never be hit, so they're left out.
+    pub fn bvh(objects: Vec<Hittable>) -> Self {
+        let objects: Vec<_> = objects
+            .into_iter()
+            .filter_map(|object| object.bounding_box().map(|bbox| (bbox, object)))
+            .collect();
+        if objects.is_empty() {
+            Self::Many(Vec::new())
+        } else {
+            Self::Bvh(BvhNode::build(objects))
+        }
+    }
+
+    /// The smallest box which contains the whole object, if it has any extent at all.
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        match self {
+            Self::Sphere(s) => Some(s.bounding_box()),
+            Self::Triangle(t) => Some(triangle_bounding_box(&t.vertices)),
+            Self::Mesh(m) => m.bounding_box(),
+            Self::Many(hittables) => hittables
+                .iter()
+                .filter_map(|hittable| hittable.bounding_box())
+                .reduce(|a, b| a.surrounding(&b)),
+            Self::Bvh(node) => Some(node.bounding_box()),
+        }
+    }
+
+    /// Every sphere and triangle in the object which gives off light.
+    pub(crate) fn emitters(&self) -> Vec<Emitter> {
+        match self {
+            Self::Sphere(s) if s.material.emitted().max_component() > 0.0 => {
+                vec![Emitter::Sphere(*s)]
+            }
+            Self::Triangle(t) if t.material.emitted().max_component() > 0.0 => {
+                Emitter::triangle(t.vertices).into_iter().collect()
+            }
+            Self::Sphere(_) | Self::Triangle(_) => Vec::new(),
+            Self::Mesh(m) => m.emitters(),
+            Self::Many(hittables) => hittables
+                .iter()
+                .flat_map(|hittable| hittable.emitters())
+                .collect(),
+            Self::Bvh(node) => node
+                .children()
+                .flat_map(|hittable| hittable.emitters())
+                .collect(),
+        }
+    }
+
     pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
         match self {
             Self::Sphere(s) => s.hit(ray, t_min, t_max),
+            Self::Triangle(t) => t.hit(ray, t_min, t_max),
+            Self::Mesh(m) => m.hit(ray, t_min, t_max),
             Self::Many(hittables) => {
                 let mut closest_so_far = t_max;
                 let mut best_hit: Option<Hit> = None;
@@ -31,10 +126,12 @@ impl Hittable {
                 }
                 best_hit
             }
+            Self::Bvh(node) => node.hit(ray, t_min, t_max),
         }
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f64,
@@ -42,8 +139,17 @@ pub struct Sphere {
 }
 
 impl Sphere {
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new_uniform(self.radius.abs());
+        Aabb {
+            min: self.center - r,
+            max: self.center + r,
+        }
+    }
+
     /// Does the ray hit this sphere?
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+    pub(crate) fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        metrics::record_primitive_test();
         let origin_to_center = ray.origin - self.center;
 
         // Use the quadratic equation's discriminant to check how many places the ray intersects
@@ -56,11 +162,15 @@ impl Sphere {
         let hit_at = |t| {
             if t < t_max && t > t_min {
                 let p = ray.point_at(t);
+                let outward_normal = (p - self.center) / self.radius;
+                let (normal, front_face) = face_towards(ray, outward_normal);
                 Some(Hit {
                     t,
                     p,
-                    normal: (p - self.center) / self.radius,
+                    normal,
+                    front_face,
                     material: self.material,
+                    uv: Uv::Sphere,
                 })
             } else {
                 None
@@ -76,3 +186,100 @@ impl Sphere {
         }
     }
 }
+
+/// Flips the surface's outward normal to point back at the ray, if the ray hit it from behind.
+/// Returns the flipped normal, and whether the ray hit the front of the surface.
+pub(crate) fn face_towards(ray: &Ray, outward_normal: Vec3) -> (Vec3, bool) {
+    if ray.direction.dot(&outward_normal) > 0.0 {
+        (-outward_normal, false)
+    } else {
+        (outward_normal, true)
+    }
+}
+
+/// Texture coordinates for a point on a sphere, given its outward normal. Like latitude and
+/// longitude: u goes around the sphere starting from -x, v goes from the bottom to the top.
+fn sphere_uv(normal: &Vec3) -> (f64, f64) {
+    let theta = (-normal.y).clamp(-1.0, 1.0).acos();
+    let phi = (-normal.z).atan2(normal.x) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+/// A single flat triangle. For lots of triangles which share vertices, a `Mesh` uses much less
+/// memory. The normal points towards whichever side the vertices go counter-clockwise around.
+#[derive(Clone, Copy)]
+pub struct Triangle {
+    pub vertices: [Vec3; 3],
+    pub material: Material,
+}
+
+impl Triangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        let (t, u, v) = intersect_triangle(ray, &self.vertices, t_min, t_max)?;
+        let (normal, front_face) = face_towards(ray, triangle_normal(&self.vertices));
+        Some(Hit {
+            t,
+            p: ray.point_at(t),
+            normal,
+            front_face,
+            material: self.material,
+            uv: Uv::Known(u, v),
+        })
+    }
+}
+
+/// Where does the ray hit the triangle, if it does? Returns the distance along the ray, and the
+/// barycentric coordinates (u, v) of the hit, i.e. how far it is towards the second and third
+/// vertices. Uses the Möller–Trumbore algorithm, which doesn't need the triangle's plane.
+pub(crate) fn intersect_triangle(
+    ray: &Ray,
+    [a, b, c]: &[Vec3; 3],
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    metrics::record_primitive_test();
+    let edge1 = *b - *a;
+    let edge2 = *c - *a;
+    let p = ray.direction.cross(edge2);
+    let determinant = edge1.dot(&p);
+    // The ray is parallel to the triangle.
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inverse_determinant = 1.0 / determinant;
+
+    let from_a = ray.origin - *a;
+    let u = from_a.dot(&p) * inverse_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = from_a.cross(edge1);
+    let v = ray.direction.dot(&q) * inverse_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(&q) * inverse_determinant;
+    if t < t_max && t > t_min {
+        Some((t, u, v))
+    } else {
+        None
+    }
+}
+
+/// Unit normal of a flat triangle.
+pub(crate) fn triangle_normal([a, b, c]: &[Vec3; 3]) -> Vec3 {
+    (*b - *a).cross(*c - *a).unit()
+}
+
+pub(crate) fn triangle_bounding_box([a, b, c]: &[Vec3; 3]) -> Aabb {
+    let bbox = Aabb { min: *a, max: *a }
+        .surrounding(&Aabb { min: *b, max: *b })
+        .surrounding(&Aabb { min: *c, max: *c });
+    // Triangles lined up with an axis have flat boxes, which rays can't pass through, so give
+    // them a bit of thickness.
+    let padding = Vec3::new_uniform(1e-4);
+    Aabb {
+        min: bbox.min - padding,
+        max: bbox.max + padding,
+    }
+}
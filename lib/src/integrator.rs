@@ -0,0 +1,333 @@
+//! Integrators decide what each camera ray sees. The path tracer is the one that makes pretty
+//! pictures, the others show one property of the scene at a time, which is handy for debugging.
+use crate::material::{random_unit_vector, Material};
+use crate::metrics;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use crate::vector::Vec3;
+use rand::Rng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use std::f64::consts::PI;
+
+pub trait Integrator: Sync {
+    /// The linear radiance seen along `ray`, i.e. the color of that point in the image.
+    fn radiance(&self, ray: &Ray, scene: &Scene, rng: &mut Xoshiro256PlusPlus) -> Vec3;
+}
+
+/// All the built-in integrators, so that one can be chosen at runtime, e.g. from a scene file.
+#[derive(Clone, Copy)]
+pub enum AnyIntegrator {
+    Path(PathTracer),
+    Normals(Normals),
+    Depth(Depth),
+    AmbientOcclusion(AmbientOcclusion),
+}
+
+impl Integrator for AnyIntegrator {
+    fn radiance(&self, ray: &Ray, scene: &Scene, rng: &mut Xoshiro256PlusPlus) -> Vec3 {
+        match self {
+            Self::Path(i) => i.radiance(ray, scene, rng),
+            Self::Normals(i) => i.radiance(ray, scene, rng),
+            Self::Depth(i) => i.radiance(ray, scene, rng),
+            Self::AmbientOcclusion(i) => i.radiance(ray, scene, rng),
+        }
+    }
+}
+
+/// Follows paths of light as they bounce around the scene.
+#[derive(Clone, Copy)]
+pub struct PathTracer {
+    /// How long paths can bounce around the scene for.
+    pub path: PathSettings,
+    /// At each diffuse surface, send a ray straight at one of the scene's emitters, instead of
+    /// only finding light when a bounce happens to hit it. Small lights converge much faster.
+    pub sample_lights: bool,
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer {
+            path: PathSettings::default(),
+            sample_lights: true,
+        }
+    }
+}
+
+/// Limits on how many times a path can bounce around the scene before we stop following it.
+#[derive(Clone, Copy, Debug)]
+pub struct PathSettings {
+    /// Paths are cut off after bouncing this many times.
+    pub max_depth: usize,
+    /// If set, paths which aren't carrying much light any more are randomly cut off early.
+    pub russian_roulette: Option<RussianRoulette>,
+}
+
+impl Default for PathSettings {
+    fn default() -> Self {
+        PathSettings {
+            max_depth: 50,
+            russian_roulette: None,
+        }
+    }
+}
+
+/// After a few bounces, most paths have been mostly absorbed and barely add anything to the image,
+/// but they cost just as much to follow. Russian roulette cuts each path off at random, more
+/// likely the less light it's carrying, and boosts the paths that survive to make up for the ones
+/// that didn't. The image comes out the same brightness, just a bit noisier, and much faster.
+#[derive(Clone, Copy, Debug)]
+pub struct RussianRoulette {
+    /// Don't play roulette until the path has bounced this many times.
+    pub start_depth: usize,
+}
+
+/// Even paths carrying almost no light survive this often, so the survivors don't get boosted so
+/// much that they show up as bright speckles.
+const MIN_SURVIVAL_PROBABILITY: f64 = 0.05;
+
+impl Integrator for PathTracer {
+    fn radiance(&self, ray: &Ray, scene: &Scene, rng: &mut Xoshiro256PlusPlus) -> Vec3 {
+        // This used to be recursive, which was a bit more readable, but it meant long paths could
+        // overflow the stack. Instead, follow the path one bounce at a time, keeping track of:
+        //  - radiance: all the light found along the path so far
+        //  - throughput: how much of the light reaching the current ray makes it back to the
+        //    camera, i.e. all the attenuations of the bounces so far multiplied together.
+        let mut radiance = Vec3::default();
+        let mut throughput = Vec3::new_uniform(1.0);
+        let mut ray = *ray;
+        let mut depth = 0;
+        // If the last bounce was off a diffuse surface while sampling lights, the probability
+        // density of it bouncing in the direction it did. See `mis_weight`.
+        let mut last_diffuse_pdf = None;
+        loop {
+            metrics::record_ray();
+            // If the ray doesn't hit anything, it sees the background, and the path ends.
+            let hit = match scene.hit(&ray, 0.001, f64::MAX) {
+                Some(hit) => hit,
+                None => {
+                    metrics::record_path(depth, false);
+                    return radiance + throughput * scene.background.color(&ray).vec();
+                }
+            };
+
+            // The object it hits might give off some light of its own. If we're sampling lights,
+            // the last bounce already had a go at finding this light, so the two estimates have to
+            // be weighted against each other.
+            let mut emitted = hit.material.emitted();
+            if let Some(diffuse_pdf) = last_diffuse_pdf {
+                let light_pdf = scene.emitter_pdf(ray.origin, ray.direction);
+                emitted *= mis_weight(diffuse_pdf, light_pdf);
+            }
+            radiance += throughput * emitted;
+            if depth >= self.path.max_depth {
+                metrics::record_path(depth, true);
+                return radiance;
+            }
+
+            // Lights can't be hit by bouncing rays, so check which of them can see this point.
+            if let Material::Diffuse { albedo } = hit.material {
+                radiance += throughput * direct_light(scene, &hit.p, &hit.normal, albedo);
+            }
+
+            // Send a ray straight at an emitter, and see if anything's in the way.
+            let sampled_emitter = match hit.material {
+                Material::Diffuse { albedo }
+                    if self.sample_lights && !scene.emitters.is_empty() =>
+                {
+                    let light = sample_emitter(scene, &hit.p, &hit.normal, albedo, rng);
+                    radiance += throughput * light;
+                    true
+                }
+                _ => false,
+            };
+
+            // Then the ray bounces off the object, unless the object absorbs it.
+            let scatter = match hit.material.scatter(&ray, &hit, rng) {
+                Some(scatter) => scatter,
+                None => {
+                    metrics::record_path(depth, false);
+                    return radiance;
+                }
+            };
+            last_diffuse_pdf = if sampled_emitter {
+                Some(diffuse_pdf(&hit.normal, &scatter.scattered.direction))
+            } else {
+                None
+            };
+            throughput *= scatter.attenuation;
+            if let Some(roulette) = self.path.russian_roulette {
+                if depth >= roulette.start_depth {
+                    let survival = throughput
+                        .max_component()
+                        .clamp(MIN_SURVIVAL_PROBABILITY, 1.0);
+                    if rng.gen::<f64>() >= survival {
+                        metrics::record_path(depth, false);
+                        return radiance;
+                    }
+                    throughput /= survival;
+                }
+            }
+            ray = scatter.scattered;
+            depth += 1;
+        }
+    }
+}
+
+/// The light arriving at point `p` on a diffuse surface straight from the scene's lights, and
+/// reflected back along the path.
+fn direct_light(scene: &Scene, p: &Vec3, normal: &Vec3, albedo: Vec3) -> Vec3 {
+    let brdf = albedo / PI;
+    scene
+        .lights
+        .iter()
+        .filter_map(|light| light.illuminate(*p))
+        .filter_map(|illumination| {
+            let cosine = normal.unit().dot(&illumination.direction);
+            if cosine <= 0.0 {
+                return None;
+            }
+            metrics::record_ray();
+            let shadow_ray = Ray {
+                origin: *p,
+                direction: illumination.direction,
+            };
+            let in_shadow = scene
+                .hit(&shadow_ray, 0.001, illumination.distance - 0.001)
+                .is_some();
+            if in_shadow {
+                None
+            } else {
+                Some(illumination.irradiance * brdf * cosine)
+            }
+        })
+        .sum()
+}
+
+/// The light arriving at point `p` on a diffuse surface straight from one of the scene's
+/// emitters, and reflected back along the path.
+fn sample_emitter(
+    scene: &Scene,
+    p: &Vec3,
+    normal: &Vec3,
+    albedo: Vec3,
+    rng: &mut Xoshiro256PlusPlus,
+) -> Vec3 {
+    let (direction, light_pdf) = match scene.sample_emitter(*p, rng) {
+        Some(sample) => sample,
+        None => return Vec3::default(),
+    };
+    let cosine = normal.unit().dot(&direction.unit());
+    if cosine <= 0.0 || light_pdf <= 0.0 {
+        return Vec3::default();
+    }
+    metrics::record_ray();
+    let shadow_ray = Ray {
+        origin: *p,
+        direction,
+    };
+    // If the first thing the ray hits doesn't give off light, the light is in shadow.
+    let light = match scene.hit(&shadow_ray, 0.001, f64::MAX) {
+        Some(hit) => hit.material.emitted(),
+        None => return Vec3::default(),
+    };
+    let brdf = albedo / PI;
+    light * brdf * (cosine / light_pdf * mis_weight(light_pdf, diffuse_pdf(normal, &direction)))
+}
+
+/// Probability density (per unit solid angle) of a diffuse surface scattering light in
+/// `direction`.
+fn diffuse_pdf(normal: &Vec3, direction: &Vec3) -> f64 {
+    normal.unit().dot(&direction.unit()).max(0.0) / PI
+}
+
+/// Light can be found two ways: by sampling a light directly, or by bouncing off a surface and
+/// hitting it. Sampling lights is better for small lights, bouncing is better for big lights seen
+/// off shiny surfaces. Multiple importance sampling combines both, weighting each by how likely
+/// it was to find that light compared to the other (Veach's power heuristic). `pdf` is the
+/// probability density of the way it was actually found, `other_pdf` of the other way.
+fn mis_weight(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0.0 {
+        0.0
+    } else {
+        a / (a + b)
+    }
+}
+
+/// Colors each object by the direction its surface faces: x, y and z become red, green and blue.
+/// Rays which miss everything are black.
+#[derive(Clone, Copy, Default)]
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn radiance(&self, ray: &Ray, scene: &Scene, _rng: &mut Xoshiro256PlusPlus) -> Vec3 {
+        metrics::record_ray();
+        metrics::record_path(0, false);
+        match scene.hit(ray, 0.001, f64::MAX) {
+            // Normals go from -1 to 1, colors go from 0 to 1.
+            Some(hit) => (hit.normal.unit() + Vec3::new_uniform(1.0)) * 0.5,
+            None => Vec3::default(),
+        }
+    }
+}
+
+/// Shows how far away each object is: white right in front of the camera, fading to black at
+/// `max_distance` and beyond.
+#[derive(Clone, Copy)]
+pub struct Depth {
+    pub max_distance: f64,
+}
+
+impl Integrator for Depth {
+    fn radiance(&self, ray: &Ray, scene: &Scene, _rng: &mut Xoshiro256PlusPlus) -> Vec3 {
+        metrics::record_ray();
+        metrics::record_path(0, false);
+        match scene.hit(ray, 0.001, f64::MAX) {
+            Some(hit) => {
+                let distance = hit.t * ray.direction.length();
+                Vec3::new_uniform(1.0 - (distance / self.max_distance).min(1.0))
+            }
+            None => Vec3::default(),
+        }
+    }
+}
+
+/// Shows how much each point is tucked away in corners and crevices, by casting rays out from it
+/// and checking how many of them hit something nearby. Open surfaces are white, occluded ones
+/// are darker. This ignores materials and lights entirely, so it's a quick way to check a scene's
+/// geometry.
+#[derive(Clone, Copy)]
+pub struct AmbientOcclusion {
+    /// How many rays to cast from each point.
+    pub samples: usize,
+    /// Objects further away than this don't count as occluding the point.
+    pub distance: f64,
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, ray: &Ray, scene: &Scene, rng: &mut Xoshiro256PlusPlus) -> Vec3 {
+        metrics::record_ray();
+        metrics::record_path(0, false);
+        let hit = match scene.hit(ray, 0.001, f64::MAX) {
+            Some(hit) => hit,
+            None => return Vec3::default(),
+        };
+        // The normal faces the camera, so these rays leave from the side of the surface it sees.
+        let normal = hit.normal.unit();
+        let samples = self.samples.max(1);
+        let unoccluded = (0..samples)
+            .filter(|_| {
+                metrics::record_ray();
+                // Same distribution that diffuse materials scatter light in, i.e. cosine-weighted
+                // around the normal.
+                let occlusion_ray = Ray {
+                    origin: hit.p,
+                    direction: normal + random_unit_vector(rng),
+                };
+                let t_max = self.distance / occlusion_ray.direction.length();
+                scene.hit(&occlusion_ray, 0.001, t_max).is_none()
+            })
+            .count();
+        Vec3::new_uniform(unoccluded as f64 / samples as f64)
+    }
+}
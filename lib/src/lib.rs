@@ -1,9 +1,22 @@
+pub mod bvh;
 pub mod camera;
 pub mod color;
+pub mod error;
+pub mod gltf_import;
 pub mod grid;
 pub mod hittable;
+pub mod integrator;
+pub mod light;
 pub mod material;
+pub mod mesh;
 pub mod metrics;
+pub mod obj;
+pub mod output;
+pub mod progress;
 pub mod ray;
 pub mod render;
+pub mod scene;
+pub mod scene_file;
+pub mod tile;
+pub mod tonemap;
 pub mod vector;
@@ -0,0 +1,112 @@
+use crate::vector::Vec3;
+
+/// Lights which aren't objects in the scene, so rays can't hit them, they just light up whatever
+/// they can see. Quicker to set up (and less noisy) than emissive objects, but they make perfectly
+/// sharp shadows, because they're infinitely small.
+#[derive(Clone, Copy, Debug)]
+pub enum Light {
+    /// Shines equally in every direction from a single point, like a bare light bulb.
+    Point {
+        position: Vec3,
+        /// Light given off in each direction. The light reaching an object falls off with the
+        /// square of its distance.
+        intensity: Vec3,
+    },
+    /// Shines from a single point, but only in a cone, like a torch or stage light.
+    Spot {
+        position: Vec3,
+        /// Which way the cone points.
+        direction: Vec3,
+        intensity: Vec3,
+        /// Angle between the middle of the cone and its edge, in degrees. No light goes outside it.
+        cone_angle: f64,
+        /// Light fades out smoothly from this angle (in degrees) to the edge of the cone.
+        falloff_start: f64,
+    },
+    /// Light coming from really far away, so it's equally bright everywhere and always comes from
+    /// the same direction, like the sun.
+    Directional {
+        /// Which way the light is travelling, e.g. (0, -1, 0) for a sun directly overhead.
+        direction: Vec3,
+        /// Light reaching each object, if it faces straight into the light.
+        irradiance: Vec3,
+    },
+}
+
+/// The light reaching a point from a `Light`.
+pub struct Illumination {
+    /// Unit vector pointing from the point back towards the light.
+    pub direction: Vec3,
+    /// How far away the light is. Anything closer than this along `direction` casts a shadow.
+    pub distance: f64,
+    /// Light arriving at the point, if it faces straight into the light.
+    pub irradiance: Vec3,
+}
+
+impl Light {
+    /// How much light reaches point `p`, ignoring anything in the way. None if it's out of the
+    /// light's reach, e.g. outside a spotlight's cone.
+    pub fn illuminate(&self, p: Vec3) -> Option<Illumination> {
+        match *self {
+            Self::Point {
+                position,
+                intensity,
+            } => {
+                let to_light = position - p;
+                let distance = to_light.length();
+                Some(Illumination {
+                    direction: to_light / distance,
+                    distance,
+                    irradiance: intensity / (distance * distance),
+                })
+            }
+            Self::Spot {
+                position,
+                direction,
+                intensity,
+                cone_angle,
+                falloff_start,
+            } => {
+                let to_light = position - p;
+                let distance = to_light.length();
+                let to_light = to_light / distance;
+                let cosine = -to_light.dot(&direction.unit());
+                let falloff = spot_falloff(
+                    cosine,
+                    cone_angle.to_radians().cos(),
+                    falloff_start.to_radians().cos(),
+                );
+                if falloff <= 0.0 {
+                    return None;
+                }
+                Some(Illumination {
+                    direction: to_light,
+                    distance,
+                    irradiance: intensity * (falloff / (distance * distance)),
+                })
+            }
+            Self::Directional {
+                direction,
+                irradiance,
+            } => Some(Illumination {
+                direction: -direction.unit(),
+                distance: f64::INFINITY,
+                irradiance,
+            }),
+        }
+    }
+}
+
+/// How much of a spotlight's intensity goes in a direction, given the cosine of the angle between
+/// that direction and the middle of the cone. Fades smoothly to nothing between the falloff angle
+/// and the cone's edge.
+fn spot_falloff(cosine: f64, cos_cone_angle: f64, cos_falloff_start: f64) -> f64 {
+    if cosine < cos_cone_angle {
+        0.0
+    } else if cosine >= cos_falloff_start {
+        1.0
+    } else {
+        let t = (cosine - cos_cone_angle) / (cos_falloff_start - cos_cone_angle);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
@@ -1,12 +1,26 @@
 use crate::hittable::Hit;
 use crate::ray::Ray;
 use crate::vector::Vec3;
-use rand::{thread_rng, Rng};
+use rand::Rng;
+use rand_xoshiro::Xoshiro256PlusPlus;
 
 #[derive(Clone, Copy)]
 pub enum Material {
-    Diffuse { albedo: Vec3 },
-    Metal { albedo: Vec3, fuzz: f64 },
+    Diffuse {
+        albedo: Vec3,
+    },
+    Metal {
+        albedo: Vec3,
+        fuzz: f64,
+    },
+    /// Clear materials like glass or water, which both reflect and refract light.
+    Dielectric {
+        refraction_index: f64,
+    },
+    /// Light sources, like lamps or glowing panels. They emit light but don't scatter any.
+    Emissive {
+        radiance: Vec3,
+    },
 }
 
 pub struct Scatter {
@@ -15,10 +29,25 @@ pub struct Scatter {
 }
 
 impl Material {
-    pub fn scatter(&self, ray_in: &Ray, hit: &Hit) -> Option<Scatter> {
+    /// How much light does this material give off by itself?
+    pub fn emitted(&self) -> Vec3 {
+        match self {
+            Self::Emissive { radiance } => *radiance,
+            _ => Vec3::zero(),
+        }
+    }
+
+    pub fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit: &Hit,
+        rng: &mut Xoshiro256PlusPlus,
+    ) -> Option<Scatter> {
         match self {
             Self::Diffuse { albedo } => {
-                let target = hit.p + hit.normal + random_point_in_unit_sphere();
+                // Adding a random unit vector to the normal scatters light in proportion to the
+                // cosine of the angle with the normal, i.e. a perfect Lambertian surface.
+                let target = hit.p + hit.normal + random_unit_vector(rng);
                 Some(Scatter {
                     attenuation: *albedo,
                     scattered: Ray {
@@ -35,19 +64,75 @@ impl Material {
                         attenuation: *albedo,
                         scattered: Ray {
                             origin: hit.p,
-                            direction: reflected + random_point_in_unit_sphere() * *fuzz,
+                            direction: reflected + random_point_in_unit_sphere(rng) * *fuzz,
                         },
                     })
                 } else {
                     None
                 }
             }
+
+            Self::Dielectric { refraction_index } => {
+                let reflected = ray_in.direction.reflect(&hit.normal);
+                let cosine = -ray_in.direction.dot(&hit.normal) / ray_in.direction.length();
+
+                // Is the ray entering the object, or leaving it? Schlick's approximation wants the
+                // angle on the outside of the object, so when leaving, use the angle the refracted
+                // ray would leave at. If it can't leave at all, the ray reflects whatever this is.
+                let (ni_over_nt, cosine) = if hit.front_face {
+                    (1.0 / refraction_index, cosine)
+                } else {
+                    let sin_squared = refraction_index.powf(2.0) * (1.0 - cosine.powf(2.0));
+                    (*refraction_index, (1.0 - sin_squared).max(0.0).sqrt())
+                };
+
+                // If the ray can't refract (total internal reflection), it has to reflect.
+                // Otherwise, pick between reflecting and refracting, weighted by how much light
+                // the surface reflects at this angle.
+                let reflect_prob = schlick(cosine, *refraction_index);
+                let direction = match ray_in.direction.refract(&hit.normal, ni_over_nt) {
+                    Some(refracted) if rng.gen::<f64>() >= reflect_prob => refracted,
+                    _ => reflected,
+                };
+                Some(Scatter {
+                    attenuation: Vec3::new_uniform(1.0),
+                    scattered: Ray {
+                        origin: hit.p,
+                        direction,
+                    },
+                })
+            }
+
+            Self::Emissive { .. } => None,
+        }
+    }
+}
+
+/// Schlick's approximation of the Fresnel equations, i.e. how much light gets reflected
+/// (rather than refracted) when it hits a dielectric at the given angle.
+fn schlick(cosine: f64, refraction_index: f64) -> f64 {
+    let r0 = ((1.0 - refraction_index) / (1.0 + refraction_index)).powf(2.0);
+    r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
+}
+
+/// A random direction, equally likely to point anywhere.
+pub fn random_unit_vector(rng: &mut Xoshiro256PlusPlus) -> Vec3 {
+    loop {
+        let p = Vec3::new(
+            rng.gen::<f64>() * 2.0 - 1.0,
+            rng.gen::<f64>() * 2.0 - 1.0,
+            rng.gen::<f64>() * 2.0 - 1.0,
+        );
+        // Only keep points inside the unit sphere, so every direction is equally likely. Points
+        // too close to the center can't be normalized accurately.
+        let squared_length = p.squared_length();
+        if squared_length < 1.0 && squared_length > 1e-12 {
+            return p / squared_length.sqrt();
         }
     }
 }
 
-pub fn random_point_in_unit_sphere() -> Vec3 {
-    let mut rng = thread_rng();
+pub fn random_point_in_unit_sphere(rng: &mut Xoshiro256PlusPlus) -> Vec3 {
     loop {
         // Pick a random line which might intersect the unit sphere
         let x = rng.gen::<f64>() * 2.0 - 1.0;
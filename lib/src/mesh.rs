@@ -0,0 +1,290 @@
+use crate::bvh::Aabb;
+use crate::hittable::{
+    face_towards, intersect_triangle, triangle_bounding_box, triangle_normal, Hit, Uv,
+};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Emitter;
+use crate::vector::Vec3;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+/// The vertices and triangles of a mesh. Kept behind an `Arc`, so several meshes (e.g. the same
+/// model in different materials) can share one copy.
+#[derive(Debug, Default)]
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    /// One per position. If present, normals are blended across each triangle, so curved
+    /// surfaces look smooth instead of faceted.
+    pub normals: Option<Vec<Vec3>>,
+    /// Texture coordinates, one per position.
+    pub uvs: Option<Vec<(f64, f64)>>,
+    /// Each triangle's three vertices, as indices into `positions` (and `normals` and `uvs`).
+    /// The flat normal points towards whichever side the vertices go counter-clockwise around.
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl MeshData {
+    /// Checks every triangle refers to vertices which exist, and every vertex has a normal and UV
+    /// (if the mesh has any).
+    pub fn validate(&self) -> Result<(), MeshError> {
+        let vertices = self.positions.len();
+        if let Some(normals) = &self.normals {
+            if normals.len() != vertices {
+                return Err(MeshError::AttributeCount {
+                    attribute: "normals",
+                    count: normals.len(),
+                    vertices,
+                });
+            }
+        }
+        if let Some(uvs) = &self.uvs {
+            if uvs.len() != vertices {
+                return Err(MeshError::AttributeCount {
+                    attribute: "UVs",
+                    count: uvs.len(),
+                    vertices,
+                });
+            }
+        }
+        for (i, triangle) in self.triangles.iter().enumerate() {
+            if let Some(&index) = triangle.iter().find(|&&index| index >= vertices) {
+                return Err(MeshError::MissingVertex {
+                    triangle: i,
+                    index,
+                    vertices,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn corners(&self, triangle: usize) -> [Vec3; 3] {
+        let [a, b, c] = self.triangles[triangle];
+        [self.positions[a], self.positions[b], self.positions[c]]
+    }
+}
+
+/// Why some `MeshData` can't be made into a mesh.
+#[derive(Debug)]
+pub enum MeshError {
+    /// The mesh has normals or UVs, but not exactly one per position.
+    AttributeCount {
+        attribute: &'static str,
+        count: usize,
+        vertices: usize,
+    },
+    /// A triangle uses a vertex which doesn't exist.
+    MissingVertex {
+        triangle: usize,
+        index: usize,
+        vertices: usize,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::AttributeCount {
+                attribute,
+                count,
+                vertices,
+            } => write!(
+                f,
+                "mesh has {} {} but {} positions",
+                count, attribute, vertices
+            ),
+            Self::MissingVertex {
+                triangle,
+                index,
+                vertices,
+            } => write!(
+                f,
+                "triangle {} uses vertex {}, but there are only {} vertices",
+                triangle, index, vertices
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// A triangle mesh, e.g. a model loaded from a file. It keeps its own BVH over its triangles, so
+/// big meshes are just as quick to hit as the rest of the scene.
+pub struct Mesh {
+    data: Arc<MeshData>,
+    material: Material,
+    /// Indices of the triangles, sorted so that each BVH leaf's triangles are next to each other.
+    order: Vec<usize>,
+    /// The BVH, with the root first. Empty if the mesh has no triangles.
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    bbox: Aabb,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    /// Holds `order[start..start + count]`.
+    Leaf { start: usize, count: usize },
+    /// Indices of the two children in `nodes`.
+    Inner { left: usize, right: usize },
+}
+
+/// Leaves hold up to this many triangles. Checking a few triangles is quicker than descending
+/// another couple of levels.
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+/// Median splits halve the triangles at each level, so the tree can't be deeper than this.
+const MAX_DEPTH: usize = 64;
+
+impl Mesh {
+    pub fn new(data: Arc<MeshData>, material: Material) -> Result<Self, MeshError> {
+        data.validate()?;
+        let boxes: Vec<_> = (0..data.triangles.len())
+            .map(|i| triangle_bounding_box(&data.corners(i)))
+            .collect();
+        let mut order: Vec<_> = (0..data.triangles.len()).collect();
+        let mut nodes = Vec::new();
+        if !order.is_empty() {
+            build(&mut nodes, &boxes, &mut order, 0);
+        }
+        Ok(Mesh {
+            data,
+            material,
+            order,
+            nodes,
+        })
+    }
+
+    pub fn data(&self) -> &Arc<MeshData> {
+        &self.data
+    }
+
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        self.nodes.first().map(|root| root.bbox)
+    }
+
+    /// Every triangle, if the mesh gives off light.
+    pub(crate) fn emitters(&self) -> Vec<Emitter> {
+        if self.material.emitted().max_component() <= 0.0 {
+            return Vec::new();
+        }
+        (0..self.data.triangles.len())
+            .filter_map(|i| Emitter::triangle(self.data.corners(i)))
+            .collect()
+    }
+
+    pub(crate) fn hit(&self, ray: &Ray, t_min: f64, mut t_max: f64) -> Option<Hit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        // Closest hit so far: the triangle, and where on it.
+        let mut closest = None;
+        let mut stack = [0; MAX_DEPTH + 1];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let node = &self.nodes[stack[stack_len]];
+            if !node.bbox.hit(ray, t_min, t_max) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, count } => {
+                    for &triangle in &self.order[start..start + count] {
+                        let corners = self.data.corners(triangle);
+                        if let Some((t, u, v)) = intersect_triangle(ray, &corners, t_min, t_max) {
+                            t_max = t;
+                            closest = Some((triangle, t, u, v));
+                        }
+                    }
+                }
+                NodeKind::Inner { left, right } => {
+                    stack[stack_len] = left;
+                    stack[stack_len + 1] = right;
+                    stack_len += 2;
+                }
+            }
+        }
+
+        let (triangle, t, u, v) = closest?;
+        let [a, b, c] = self.data.triangles[triangle];
+        // Blend each vertex's attributes by how close the hit is to it.
+        let w = 1.0 - u - v;
+        // Which side was hit depends on the triangle's real, flat surface. The blended normal
+        // then gets flipped to the same side.
+        let (flat_normal, front_face) =
+            face_towards(ray, triangle_normal(&self.data.corners(triangle)));
+        let normal = match &self.data.normals {
+            Some(normals) => {
+                let normal = (normals[a] * w + normals[b] * u + normals[c] * v).unit();
+                if front_face {
+                    normal
+                } else {
+                    -normal
+                }
+            }
+            None => flat_normal,
+        };
+        let uv = match &self.data.uvs {
+            Some(uvs) => Uv::Known(
+                uvs[a].0 * w + uvs[b].0 * u + uvs[c].0 * v,
+                uvs[a].1 * w + uvs[b].1 * u + uvs[c].1 * v,
+            ),
+            None => Uv::Known(u, v),
+        };
+        Some(Hit {
+            t,
+            p: ray.point_at(t),
+            normal,
+            front_face,
+            material: self.material,
+            uv,
+        })
+    }
+}
+
+/// Adds a node for the triangles in `order` (which start at `start` in the mesh's whole `order`)
+/// and everything underneath it. Returns the new node's index. Splits at the median along the
+/// longest axis, like `BvhNode::build`.
+fn build(nodes: &mut Vec<Node>, boxes: &[Aabb], order: &mut [usize], start: usize) -> usize {
+    let bbox = order
+        .iter()
+        .map(|&i| boxes[i])
+        .reduce(|a, b| a.surrounding(&b))
+        .unwrap();
+    let index = nodes.len();
+    nodes.push(Node {
+        bbox,
+        kind: NodeKind::Leaf {
+            start,
+            count: order.len(),
+        },
+    });
+    if order.len() <= MAX_LEAF_TRIANGLES {
+        return index;
+    }
+
+    let centroid_bounds = order
+        .iter()
+        .map(|&i| {
+            let c = boxes[i].centroid();
+            Aabb { min: c, max: c }
+        })
+        .reduce(|a, b| a.surrounding(&b))
+        .unwrap();
+    let axis = centroid_bounds.longest_axis();
+    let mid = order.len() / 2;
+    order.select_nth_unstable_by(mid, |&a, &b| {
+        boxes[a].centroid()[axis]
+            .partial_cmp(&boxes[b].centroid()[axis])
+            .unwrap_or(Ordering::Equal)
+    });
+    let (left, right) = order.split_at_mut(mid);
+    let left = build(nodes, boxes, left, start);
+    let right = build(nodes, boxes, right, start + mid);
+    nodes[index].kind = NodeKind::Inner { left, right };
+    index
+}
@@ -1,26 +1,211 @@
+use std::cell::{Cell, RefCell};
 use std::time::Duration;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Metrics {
-    // How many rays were traced in total
-    pub rays_traced_total: usize,
+    /// How many rays were traced from the camera, i.e. samples per pixel * pixels.
+    pub primary_rays: usize,
+    /// How many rays were traced in total, including every ray that bounced off an object.
+    pub rays_traced: u64,
+    /// How many times a ray was tested against an object, e.g. a sphere. Only counted with the
+    /// `detailed-metrics` feature, because counting it slows down every intersection test.
+    pub primitive_tests: u64,
+    /// How many times a ray was tested against a BVH bounding box. Only counted with the
+    /// `detailed-metrics` feature, like `primitive_tests`.
+    pub bounding_box_tests: u64,
+    /// `path_depths[n]` is how many paths bounced `n` times before they ended.
+    pub path_depths: Vec<u64>,
+    /// How many paths were cut off because they bounced too many times.
+    pub paths_terminated_by_depth: u64,
     pub time_spent: Duration,
+    /// How long each rendering thread spent actually rendering. If some threads were much less busy
+    /// than others, the work wasn't split up evenly.
+    pub thread_busy_time: Vec<Duration>,
+}
+
+// Counting into shared atomics would make every thread fight over the same cache lines, so each
+// thread counts into its own counters, and they're added into the render's metrics every so often.
+thread_local! {
+    static COUNTERS: Counters = const { Counters::new() };
+}
+
+struct Counters {
+    rays_traced: Cell<u64>,
+    primitive_tests: Cell<u64>,
+    bounding_box_tests: Cell<u64>,
+    path_depths: RefCell<Vec<u64>>,
+    paths_terminated_by_depth: Cell<u64>,
+    busy_time: Cell<Duration>,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Counters {
+            rays_traced: Cell::new(0),
+            primitive_tests: Cell::new(0),
+            bounding_box_tests: Cell::new(0),
+            path_depths: RefCell::new(Vec::new()),
+            paths_terminated_by_depth: Cell::new(0),
+            busy_time: Cell::new(Duration::ZERO),
+        }
+    }
+}
+
+fn increment(counter: &Cell<u64>) {
+    counter.set(counter.get() + 1);
+}
+
+/// Call this every time a ray is traced through the scene. The built-in integrators already do;
+/// custom ones should too, if you want their metrics to be accurate.
+pub fn record_ray() {
+    COUNTERS.with(|c| increment(&c.rays_traced));
+}
+
+/// Call this when a path ends, after bouncing `depth` times. `hit_depth_limit` is true if it was
+/// cut off, rather than escaping the scene or being absorbed.
+pub fn record_path(depth: usize, hit_depth_limit: bool) {
+    COUNTERS.with(|c| {
+        let mut depths = c.path_depths.borrow_mut();
+        if depths.len() <= depth {
+            depths.resize(depth + 1, 0);
+        }
+        depths[depth] += 1;
+        if hit_depth_limit {
+            increment(&c.paths_terminated_by_depth);
+        }
+    });
+}
+
+#[inline]
+pub(crate) fn record_primitive_test() {
+    if cfg!(feature = "detailed-metrics") {
+        COUNTERS.with(|c| increment(&c.primitive_tests));
+    }
+}
+
+#[inline]
+pub(crate) fn record_bounding_box_test() {
+    if cfg!(feature = "detailed-metrics") {
+        COUNTERS.with(|c| increment(&c.bounding_box_tests));
+    }
+}
+
+pub(crate) fn record_busy_time(time: Duration) {
+    COUNTERS.with(|c| c.busy_time.set(c.busy_time.get() + time));
+}
+
+/// Throws away whatever the current thread has counted, e.g. rays traced outside of a render, so
+/// they don't end up in the next render's metrics.
+pub(crate) fn reset_thread_counters() {
+    COUNTERS.with(|c| {
+        c.rays_traced.set(0);
+        c.primitive_tests.set(0);
+        c.bounding_box_tests.set(0);
+        c.path_depths.borrow_mut().clear();
+        c.paths_terminated_by_depth.set(0);
+        c.busy_time.set(Duration::ZERO);
+    });
 }
 
 impl Metrics {
-    pub fn new(rays_traced_total: usize) -> Self {
+    pub fn new(primary_rays: usize) -> Self {
         Metrics {
-            rays_traced_total,
-            time_spent: Duration::from_millis(0),
+            primary_rays,
+            ..Default::default()
+        }
+    }
+
+    /// Adds everything the current thread has counted since last time, and resets its counters.
+    pub(crate) fn collect_thread_counters(&mut self) {
+        COUNTERS.with(|c| {
+            self.rays_traced += c.rays_traced.take();
+            self.primitive_tests += c.primitive_tests.take();
+            self.bounding_box_tests += c.bounding_box_tests.take();
+            self.paths_terminated_by_depth += c.paths_terminated_by_depth.take();
+            let depths = c.path_depths.take();
+            if self.path_depths.len() < depths.len() {
+                self.path_depths.resize(depths.len(), 0);
+            }
+            for (total, n) in self.path_depths.iter_mut().zip(depths) {
+                *total += n;
+            }
+
+            let thread = rayon::current_thread_index().unwrap_or(0);
+            if self.thread_busy_time.len() <= thread {
+                self.thread_busy_time.resize(thread + 1, Duration::ZERO);
+            }
+            self.thread_busy_time[thread] += c.busy_time.take();
+        });
+    }
+
+    /// Average number of times each path bounced before it ended.
+    pub fn average_path_depth(&self) -> f64 {
+        let paths: u64 = self.path_depths.iter().sum();
+        let bounces: u64 = self
+            .path_depths
+            .iter()
+            .enumerate()
+            .map(|(depth, n)| depth as u64 * n)
+            .sum();
+        if paths == 0 {
+            0.0
+        } else {
+            bounces as f64 / paths as f64
         }
     }
 
     pub fn describe(&self) -> String {
-        format!(
-            "{}ns per ray\n{} rays\n{} seconds",
-            self.time_spent.as_nanos() / (self.rays_traced_total as u128),
-            self.rays_traced_total,
-            self.time_spent.as_secs(),
-        )
+        let mut s = format!(
+            "{}ns per ray\n{} primary rays\n{} rays traced\n{:.2} average bounces per path\n\
+             {} paths hit the depth limit",
+            self.time_spent.as_nanos() / (self.rays_traced.max(1) as u128),
+            self.primary_rays,
+            self.rays_traced,
+            self.average_path_depth(),
+            self.paths_terminated_by_depth,
+        );
+        if cfg!(feature = "detailed-metrics") {
+            s.push_str(&format!(
+                "\n{} primitive tests\n{} bounding box tests",
+                self.primitive_tests, self.bounding_box_tests
+            ));
+        }
+        s.push_str(&format!("\n{} seconds", self.time_spent.as_secs()));
+        s.push_str("\nbounces per path:");
+        for (depth, n) in self.path_depths.iter().enumerate() {
+            if *n > 0 {
+                s.push_str(&format!("\n  {:>3}: {}", depth, n));
+            }
+        }
+        s.push_str("\nbusy time per thread:");
+        for (thread, busy) in self.thread_busy_time.iter().enumerate() {
+            s.push_str(&format!("\n  {:>3}: {:.2}s", thread, busy.as_secs_f64()));
+        }
+        s
+    }
+
+    /// All the metrics as a JSON object, for feeding into other tools. Durations are in seconds.
+    pub fn to_json(&self) -> String {
+        let mut report = serde_json::json!({
+            "primary_rays": self.primary_rays,
+            "rays_traced": self.rays_traced,
+            "primitive_tests": self.primitive_tests,
+            "bounding_box_tests": self.bounding_box_tests,
+            "path_depths": self.path_depths,
+            "average_path_depth": self.average_path_depth(),
+            "paths_terminated_by_depth": self.paths_terminated_by_depth,
+            "time_spent_secs": self.time_spent.as_secs_f64(),
+            "thread_busy_time_secs": self
+                .thread_busy_time
+                .iter()
+                .map(Duration::as_secs_f64)
+                .collect::<Vec<_>>(),
+        });
+        if !cfg!(feature = "detailed-metrics") {
+            let report = report.as_object_mut().expect("the report is a JSON object");
+            report.remove("primitive_tests");
+            report.remove("bounding_box_tests");
+        }
+        serde_json::to_string_pretty(&report).expect("JSON values can always be serialized")
     }
 }
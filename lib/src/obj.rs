@@ -0,0 +1,598 @@
+//! Loads models from Wavefront OBJ files, which almost every 3D modelling tool can export.
+//! Materials come from the MTL files the OBJ refers to, and get mapped onto the closest `Material`:
+//! - anything with an emissive color (`Ke`) becomes `Emissive`
+//! - transparent materials (`d` or `Tr`, or `illum` 4, 6, 7 or 9) become `Dielectric`, using `Ni`
+//! - reflective materials (`illum` 3 or 5) become `Metal`, colored by `Ks`, blurred by `Ns`
+//! - everything else becomes `Diffuse`, colored by `Kd`
+//!
+//! Textures, curves, and anything else we can't render yet are skipped.
+use crate::hittable::Hittable;
+use crate::material::Material;
+use crate::mesh::{Mesh, MeshData};
+use crate::vector::Vec3;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Faces which don't have a material get this.
+pub(crate) const DEFAULT_MATERIAL: Material = Material::Diffuse {
+    albedo: Vec3 {
+        x: 0.8,
+        y: 0.8,
+        z: 0.8,
+    },
+};
+
+#[derive(Debug)]
+pub enum ObjError {
+    /// The OBJ file, or one of its MTL files, couldn't be read.
+    Io(PathBuf, std::io::Error),
+    /// A line of the OBJ or MTL file doesn't make sense.
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "couldn't read {}: {}", path.display(), err),
+            Self::Syntax { path, line, reason } => {
+                write!(f, "{}, line {}: {}", path.display(), line, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, err) => Some(err),
+            Self::Syntax { .. } => None,
+        }
+    }
+}
+
+/// Read the OBJ file at the given path, and any MTL files it uses (which are looked for next to
+/// it). Each material becomes a separate mesh. If `material` is given, the whole model uses it
+/// instead, and MTL files are ignored.
+pub fn load<P: AsRef<Path>>(path: P, material: Option<Material>) -> Result<Hittable, ObjError> {
+    let path = path.as_ref();
+    let contents = read(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse(&contents, path, |mtl| {
+        if material.is_some() {
+            return Ok(HashMap::new());
+        }
+        let mtl_path = dir.join(mtl);
+        parse_mtl(&read(&mtl_path)?, &mtl_path)
+    })
+    .map(|model| model.into_hittable(material))
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    std::fs::read_to_string(path).map_err(|err| ObjError::Io(path.to_owned(), err))
+}
+
+/// Everything in an OBJ file, with the faces grouped by material.
+struct Model {
+    groups: Vec<(Material, Group)>,
+}
+
+impl Model {
+    fn into_hittable(self, material: Option<Material>) -> Hittable {
+        let meshes = self
+            .groups
+            .into_iter()
+            .filter(|(_, group)| !group.triangles.is_empty())
+            .map(|(group_material, group)| {
+                let mesh = Mesh::new(
+                    Arc::new(group.into_mesh_data()),
+                    material.unwrap_or(group_material),
+                )
+                .expect("OBJ faces only use vertices which exist");
+                Hittable::Mesh(mesh)
+            })
+            .collect();
+        Hittable::bvh(meshes)
+    }
+}
+
+/// The faces which use one material. OBJ faces pick a position, UV and normal for each corner
+/// separately, but meshes have a single index per vertex, so each different combination becomes
+/// its own vertex.
+#[derive(Default)]
+struct Group {
+    vertices: HashMap<(usize, Option<usize>, Option<usize>), usize>,
+    positions: Vec<Vec3>,
+    uvs: Vec<Option<(f64, f64)>>,
+    normals: Vec<Option<Vec3>>,
+    triangles: Vec<[usize; 3]>,
+}
+
+impl Group {
+    fn vertex(&mut self, obj: &ObjVertices, corner: Corner) -> usize {
+        let key = (corner.position, corner.uv, corner.normal);
+        if let Some(&index) = self.vertices.get(&key) {
+            return index;
+        }
+        let index = self.positions.len();
+        self.positions.push(obj.positions[corner.position]);
+        self.uvs.push(corner.uv.map(|i| obj.uvs[i]));
+        self.normals.push(corner.normal.map(|i| obj.normals[i]));
+        self.vertices.insert(key, index);
+        index
+    }
+
+    /// Meshes either have normals (or UVs) for every vertex or none, so if some faces left them
+    /// out, the whole mesh goes without.
+    fn into_mesh_data(self) -> MeshData {
+        MeshData {
+            positions: self.positions,
+            normals: self.normals.into_iter().collect(),
+            uvs: self.uvs.into_iter().collect(),
+            triangles: self.triangles,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ObjVertices {
+    positions: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    normals: Vec<Vec3>,
+}
+
+/// One corner of a face: indices into `ObjVertices`.
+#[derive(Clone, Copy)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// Parse the contents of an OBJ file. `path` is only used for error messages. `load_mtl` is called
+/// with the name of each MTL file the OBJ uses, and returns the materials it defines.
+fn parse<F>(contents: &str, path: &Path, mut load_mtl: F) -> Result<Model, ObjError>
+where
+    F: FnMut(&str) -> Result<HashMap<String, Material>, ObjError>,
+{
+    let mut obj = ObjVertices::default();
+    let mut materials = HashMap::new();
+    let mut groups = vec![(DEFAULT_MATERIAL, Group::default())];
+    // Which group each material name's faces go in.
+    let mut group_of_material: HashMap<String, usize> = HashMap::new();
+    let mut current_group = 0;
+
+    for (i, line) in contents.lines().enumerate() {
+        let syntax_error = |reason: String| ObjError::Syntax {
+            path: path.to_owned(),
+            line: i + 1,
+            reason,
+        };
+        let mut words = strip_comment(line).split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<_> = words.collect();
+        match keyword {
+            // Positions can be followed by a weight, and some exporters add a vertex color too
+            // (`v x y z r g b`). Neither matters to us.
+            "v" => obj
+                .positions
+                .push(parse_vec3(&args, 3, 7).map_err(syntax_error)?),
+            "vt" => {
+                let uv = parse_numbers(&args, 1, 3).map_err(syntax_error)?;
+                obj.uvs.push((uv[0], uv.get(1).copied().unwrap_or(0.0)));
+            }
+            "vn" => obj
+                .normals
+                .push(parse_vec3(&args, 3, 3).map_err(syntax_error)?),
+            "f" => {
+                if args.len() < 3 {
+                    return Err(syntax_error(format!(
+                        "faces need at least 3 vertices, this one has {}",
+                        args.len()
+                    )));
+                }
+                let corners = args
+                    .iter()
+                    .map(|arg| parse_corner(arg, &obj))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(syntax_error)?;
+                let group = &mut groups[current_group].1;
+                let indices: Vec<_> = corners.iter().map(|c| group.vertex(&obj, *c)).collect();
+                // Split polygons into a fan of triangles around the first vertex.
+                for j in 1..indices.len() - 1 {
+                    group
+                        .triangles
+                        .push([indices[0], indices[j], indices[j + 1]]);
+                }
+            }
+            "mtllib" => {
+                for mtl in args {
+                    materials.extend(load_mtl(mtl)?);
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                current_group = match group_of_material.get(&name) {
+                    Some(&group) => group,
+                    None => {
+                        // If a material override is in use there are no MTL materials, but the
+                        // faces still get grouped by name.
+                        let material = match materials.get(&name) {
+                            Some(material) => *material,
+                            None if materials.is_empty() => DEFAULT_MATERIAL,
+                            None => {
+                                return Err(syntax_error(format!(
+                                    "no material called `{}` in the MTL files",
+                                    name
+                                )))
+                            }
+                        };
+                        groups.push((material, Group::default()));
+                        group_of_material.insert(name, groups.len() - 1);
+                        groups.len() - 1
+                    }
+                };
+            }
+            // Objects, groups, smoothing groups, lines, points, and curves.
+            _ => {}
+        }
+    }
+    Ok(Model { groups })
+}
+
+/// Parse the contents of an MTL file into materials, by name. `path` is only used for error
+/// messages.
+fn parse_mtl(contents: &str, path: &Path) -> Result<HashMap<String, Material>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+    for (i, line) in contents.lines().enumerate() {
+        let syntax_error = |reason: String| ObjError::Syntax {
+            path: path.to_owned(),
+            line: i + 1,
+            reason,
+        };
+        let mut words = strip_comment(line).split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<_> = words.collect();
+        if keyword == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material.to_material());
+            }
+            current = Some((args.join(" "), MtlMaterial::default()));
+            continue;
+        }
+        let material = match &mut current {
+            Some((_, material)) => material,
+            // Statements before the first `newmtl` don't belong to any material.
+            None => continue,
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_vec3(&args, 3, 3).map_err(syntax_error)?,
+            "Ks" => material.specular = parse_vec3(&args, 3, 3).map_err(syntax_error)?,
+            "Ke" => material.emissive = parse_vec3(&args, 3, 3).map_err(syntax_error)?,
+            "Ns" => material.shininess = parse_number(&args).map_err(syntax_error)?,
+            "Ni" => material.refraction_index = parse_number(&args).map_err(syntax_error)?,
+            "d" => material.opacity = parse_number(&args).map_err(syntax_error)?,
+            "Tr" => material.opacity = 1.0 - parse_number(&args).map_err(syntax_error)?,
+            "illum" => {
+                material.illum = parse_number(&args).map_err(syntax_error)? as u32;
+            }
+            // Textures and other settings we don't use.
+            _ => {}
+        }
+    }
+    if let Some((name, material)) = current {
+        materials.insert(name, material.to_material());
+    }
+    Ok(materials)
+}
+
+/// The parts of an MTL material we care about.
+struct MtlMaterial {
+    diffuse: Vec3,
+    specular: Vec3,
+    emissive: Vec3,
+    shininess: f64,
+    refraction_index: f64,
+    opacity: f64,
+    illum: u32,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            diffuse: Vec3::new_uniform(0.8),
+            specular: Vec3::zero(),
+            emissive: Vec3::zero(),
+            shininess: 0.0,
+            refraction_index: 1.5,
+            opacity: 1.0,
+            illum: 2,
+        }
+    }
+}
+
+impl MtlMaterial {
+    fn to_material(&self) -> Material {
+        let unit = |v: Vec3| {
+            Vec3::new(
+                v.x.clamp(0.0, 1.0),
+                v.y.clamp(0.0, 1.0),
+                v.z.clamp(0.0, 1.0),
+            )
+        };
+        if self.emissive.max_component() > 0.0 {
+            Material::Emissive {
+                radiance: Vec3::new(
+                    self.emissive.x.max(0.0),
+                    self.emissive.y.max(0.0),
+                    self.emissive.z.max(0.0),
+                ),
+            }
+        } else if self.opacity < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            Material::Dielectric {
+                refraction_index: if self.refraction_index > 0.0 {
+                    self.refraction_index
+                } else {
+                    1.5
+                },
+            }
+        } else if matches!(self.illum, 3 | 5) {
+            // Shinier materials (higher Ns) have sharper reflections. This is roughly how
+            // Blinn-Phong exponents map onto roughness.
+            Material::Metal {
+                albedo: unit(self.specular),
+                fuzz: (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt(),
+            }
+        } else {
+            Material::Diffuse {
+                albedo: unit(self.diffuse),
+            }
+        }
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(i) => &line[..i],
+        None => line,
+    }
+}
+
+/// Parse a face's corner, like `1`, `1/2`, `1//3` or `1/2/3`. Indices start at 1, and negative
+/// indices count back from the most recent vertex.
+fn parse_corner(arg: &str, obj: &ObjVertices) -> Result<Corner, String> {
+    let mut parts = arg.split('/');
+    let position = parse_index(parts.next().unwrap_or(""), obj.positions.len(), "position")?;
+    let uv = match parts.next() {
+        None | Some("") => None,
+        Some(part) => Some(parse_index(part, obj.uvs.len(), "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        None | Some("") => None,
+        Some(part) => Some(parse_index(part, obj.normals.len(), "normal")?),
+    };
+    if parts.next().is_some() {
+        return Err(format!("`{}` has too many parts", arg));
+    }
+    Ok(Corner {
+        position,
+        uv,
+        normal,
+    })
+}
+
+/// Turn an OBJ index into an index into the list of `len` things so far.
+fn parse_index(s: &str, len: usize, what: &str) -> Result<usize, String> {
+    let index: i64 = s
+        .parse()
+        .map_err(|_| format!("couldn't parse `{}` as a {} index", s, what))?;
+    let resolved = if index > 0 {
+        index - 1
+    } else {
+        len as i64 + index
+    };
+    if index == 0 || resolved < 0 || resolved >= len as i64 {
+        return Err(format!(
+            "{} index {} doesn't exist, there are only {} so far",
+            what, index, len
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_numbers(args: &[&str], min: usize, max: usize) -> Result<Vec<f64>, String> {
+    if args.len() < min || args.len() > max {
+        let expected = if min == max {
+            min.to_string()
+        } else {
+            format!("{} to {}", min, max)
+        };
+        return Err(format!(
+            "expected {} numbers, found {}",
+            expected,
+            args.len()
+        ));
+    }
+    args.iter()
+        .map(|arg| {
+            arg.parse()
+                .map_err(|_| format!("couldn't parse `{}` as a number", arg))
+        })
+        .collect()
+}
+
+/// The first three of between `min` and `max` numbers, e.g. `v` lines can have an optional w.
+fn parse_vec3(args: &[&str], min: usize, max: usize) -> Result<Vec3, String> {
+    let n = parse_numbers(args, min, max)?;
+    Ok(Vec3::new(n[0], n[1], n[2]))
+}
+
+fn parse_number(args: &[&str]) -> Result<f64, String> {
+    Ok(parse_numbers(args, 1, 1)?[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_obj(contents: &str) -> Result<Model, ObjError> {
+        parse(contents, Path::new("test.obj"), |mtl| {
+            Err(ObjError::Io(
+                PathBuf::from(mtl),
+                std::io::ErrorKind::NotFound.into(),
+            ))
+        })
+    }
+
+    /// Parses an OBJ file which uses the materials in `mtl`.
+    fn parse_obj_with_mtl(contents: &str, mtl: &str) -> Result<Model, ObjError> {
+        parse(contents, Path::new("test.obj"), |name| {
+            parse_mtl(mtl, Path::new(name))
+        })
+    }
+
+    fn only_mesh(model: Model) -> MeshData {
+        let mut groups: Vec<_> = model
+            .groups
+            .into_iter()
+            .filter(|(_, group)| !group.triangles.is_empty())
+            .collect();
+        assert_eq!(groups.len(), 1);
+        groups.remove(0).1.into_mesh_data()
+    }
+
+    fn material(mtl: &str) -> Material {
+        parse_mtl(&format!("newmtl m\n{}", mtl), Path::new("test.mtl")).unwrap()["m"]
+    }
+
+    #[test]
+    fn polygons_become_a_fan_of_triangles() {
+        let mesh = only_mesh(
+            parse_obj(
+                "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0.5 1.5 0\nv 0 1 0\n\
+                 f 1 2 3 4 5\n",
+            )
+            .unwrap(),
+        );
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_latest_vertex() {
+        let mesh =
+            only_mesh(parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 5 5 5\nf -4 -3 -2\n").unwrap());
+        assert_eq!(mesh.positions.len(), 3);
+        assert_eq!(mesh.positions[1].x, 1.0);
+        assert_eq!(mesh.positions[2].y, 1.0);
+    }
+
+    #[test]
+    fn vertex_colors_are_ignored() {
+        let mesh = only_mesh(
+            parse_obj("v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0 1.0 0 0 1\nf 1 2 3\n").unwrap(),
+        );
+        assert_eq!(mesh.positions[2].y, 1.0);
+        let err = parse_obj("v 0 0 0 1 0 0 1 1\n").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "test.obj, line 1: expected 3 to 7 numbers, found 8"
+        );
+    }
+
+    #[test]
+    fn corners_can_have_uvs_and_normals() {
+        let vertices = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\n";
+        let both = only_mesh(parse_obj(&format!("{}f 1/1/1 2/2/1 3/3/1\n", vertices)).unwrap());
+        assert_eq!(both.uvs.unwrap()[1], (1.0, 0.0));
+        assert_eq!(both.normals.unwrap()[2].z, 1.0);
+
+        let uvs = only_mesh(parse_obj(&format!("{}f 1/1 2/2 3/3\n", vertices)).unwrap());
+        assert!(uvs.uvs.is_some() && uvs.normals.is_none());
+
+        let normals = only_mesh(parse_obj(&format!("{}f 1//1 2//1 3//1\n", vertices)).unwrap());
+        assert!(normals.uvs.is_none() && normals.normals.is_some());
+    }
+
+    #[test]
+    fn mesh_drops_normals_if_some_faces_have_none() {
+        let mesh = only_mesh(
+            parse_obj(
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nvn 0 0 1\n\
+                 f 1//1 2//1 3//1\nf 2 4 3\n",
+            )
+            .unwrap(),
+        );
+        assert!(mesh.normals.is_none());
+        assert_eq!(mesh.triangles.len(), 2);
+        mesh.validate().unwrap();
+    }
+
+    #[test]
+    fn unknown_materials_are_errors() {
+        let obj = "mtllib test.mtl\nusemtl missing\n";
+        let err = parse_obj_with_mtl(obj, "newmtl red\nKd 1 0 0\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "test.obj, line 2: no material called `missing` in the MTL files"
+        );
+        // Without any MTL files, there's nothing to check against.
+        assert!(parse_obj("usemtl missing\n").is_ok());
+    }
+
+    #[test]
+    fn faces_are_grouped_by_material() {
+        let obj = "mtllib test.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\n\
+                   usemtl red\nf 1 2 3\nusemtl blue\nf 1 2 3\nusemtl red\nf 3 2 1\n";
+        let model =
+            parse_obj_with_mtl(obj, "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n").unwrap();
+        let groups: Vec<_> = model
+            .groups
+            .iter()
+            .filter(|(_, group)| !group.triangles.is_empty())
+            .collect();
+        assert_eq!(groups.len(), 2);
+        assert!(matches!(groups[0].0, Material::Diffuse { albedo } if albedo.x == 1.0));
+        assert_eq!(groups[0].1.triangles.len(), 2);
+        assert!(matches!(groups[1].0, Material::Diffuse { albedo } if albedo.z == 1.0));
+    }
+
+    #[test]
+    fn mtl_materials_map_onto_the_closest_material() {
+        assert!(matches!(
+            material("Kd 0.2 0.4 0.6"),
+            Material::Diffuse { albedo } if albedo.y == 0.4
+        ));
+        assert!(matches!(
+            material("Kd 1 1 1\nKe 5 4 3"),
+            Material::Emissive { radiance } if radiance.x == 5.0 && radiance.z == 3.0
+        ));
+        assert!(matches!(
+            material("d 0.5\nNi 1.33"),
+            Material::Dielectric { refraction_index } if refraction_index == 1.33
+        ));
+        assert!(matches!(
+            material("Tr 0.5"),
+            Material::Dielectric { refraction_index } if refraction_index == 1.5
+        ));
+        // Fully opaque, whichever way it's written.
+        assert!(matches!(material("Tr 0"), Material::Diffuse { .. }));
+        assert!(matches!(
+            material("illum 3\nKs 0.9 0.8 0.7\nNs 1000"),
+            Material::Metal { albedo, fuzz } if albedo.x == 0.9 && fuzz < 0.1
+        ));
+    }
+}
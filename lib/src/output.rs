@@ -0,0 +1,99 @@
+use crate::color::TransferFunction;
+use crate::error::RenderError;
+use crate::grid::{Grid, Point};
+use crate::tonemap::ToneMap;
+use crate::vector::Vec3;
+use std::path::Path;
+
+/// Image formats that renders can be saved in.
+#[derive(Clone, Copy, Debug)]
+pub enum OutputFormat {
+    /// 8-bit formats like PNG or JPEG, which can only store colors between 0 and 1.
+    Ldr(image::ImageFormat),
+    /// OpenEXR (.exr), which stores the linear radiance as 32-bit floats.
+    Exr,
+    /// Radiance HDR (.hdr), which stores the linear radiance with a shared exponent.
+    Hdr,
+}
+
+impl OutputFormat {
+    /// Work out which format to use from the path's file extension.
+    /// Returns None if it's not a format we can write.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "exr" => Some(Self::Exr),
+            "hdr" => Some(Self::Hdr),
+            _ => match image::ImageFormat::from_extension(extension) {
+                Some(format) if format.can_write() => Some(Self::Ldr(format)),
+                _ => None,
+            },
+        }
+    }
+
+    /// Write the linear radiance in the framebuffer to the given path.
+    /// `tone_map` and `transfer` are only used by 8-bit formats; HDR formats store the radiance
+    /// as-is.
+    pub fn write(
+        &self,
+        framebuffer: &Grid<Vec3>,
+        path: &Path,
+        tone_map: ToneMap,
+        transfer: TransferFunction,
+    ) -> Result<(), RenderError> {
+        match self {
+            Self::Ldr(format) => write_ldr(framebuffer, path, *format, tone_map, transfer),
+            Self::Exr => write_exr(framebuffer, path),
+            Self::Hdr => write_hdr(framebuffer, path),
+        }
+    }
+}
+
+fn write_ldr(
+    framebuffer: &Grid<Vec3>,
+    path: &Path,
+    format: image::ImageFormat,
+    tone_map: ToneMap,
+    transfer: TransferFunction,
+) -> Result<(), RenderError> {
+    let mut img_buf =
+        image::ImageBuffer::new(framebuffer.width() as u32, framebuffer.height() as u32);
+    for (x, y, pixel) in img_buf.enumerate_pixels_mut() {
+        let radiance = framebuffer.get(&Point {
+            x: x as usize,
+            y: y as usize,
+        });
+        *pixel = image::Rgb(tone_map.apply(*radiance).to_rgb_encoded(transfer));
+    }
+    img_buf.save_with_format(path, format)?;
+    Ok(())
+}
+
+fn write_exr(framebuffer: &Grid<Vec3>, path: &Path) -> Result<(), RenderError> {
+    exr::prelude::write_rgb_file(path, framebuffer.width(), framebuffer.height(), |x, y| {
+        let radiance = framebuffer.get(&Point { x, y });
+        (radiance.x as f32, radiance.y as f32, radiance.z as f32)
+    })?;
+    Ok(())
+}
+
+fn write_hdr(framebuffer: &Grid<Vec3>, path: &Path) -> Result<(), RenderError> {
+    let mut pixels = Vec::with_capacity(framebuffer.size());
+    for y in 0..framebuffer.height() {
+        for x in 0..framebuffer.width() {
+            let radiance = framebuffer.get(&Point { x, y });
+            pixels.push(image::Rgb([
+                radiance.x as f32,
+                radiance.y as f32,
+                radiance.z as f32,
+            ]));
+        }
+    }
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    image::codecs::hdr::HdrEncoder::new(file).encode(
+        &pixels,
+        framebuffer.width(),
+        framebuffer.height(),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use crate::tile::Tile;
+use crate::vector::Vec3;
+
+/// Gets told how a render is going, e.g. to draw a progress bar or show a preview.
+/// Methods get called from the rendering threads, so they should be quick.
+pub trait ProgressObserver: Sync {
+    /// Called once, before anything is rendered.
+    /// `total_tiles` counts every tile of every pass, and `total_samples` is the number of samples
+    /// (i.e. rays from the camera) that will be traced for the whole image.
+    fn started(&self, _total_tiles: usize, _total_samples: usize) {}
+
+    /// Called every time a tile is finished. When rendering progressively, this is called for
+    /// every tile once per pass.
+    fn tile_done(&self, _progress: &TileProgress) {}
+
+    /// Called once every pixel has all its samples.
+    fn finished(&self) {}
+}
+
+/// Ignores all progress.
+impl ProgressObserver for () {}
+
+/// A tile which just finished rendering.
+pub struct TileProgress<'a> {
+    pub tile: &'a Tile,
+    /// The radiance of each pixel in the tile so far, row by row.
+    pub pixels: &'a [Vec3],
+    /// How many samples each pixel got while rendering the tile this time.
+    pub samples: usize,
+}
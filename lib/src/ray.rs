@@ -1,6 +1,7 @@
 use crate::vector::Vec3;
 
 /// Rays are just finite lines, i.e. lines with a start and a direction.
+#[derive(Clone, Copy, Debug)]
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
@@ -1,129 +1,248 @@
-use crate::color::Color;
+use crate::color::TransferFunction;
+use crate::error::RenderError;
 use crate::grid::Point;
-use crate::hittable::Hittable;
-use crate::metrics::Metrics;
-use crate::ray::Ray;
+use crate::integrator::Integrator;
+use crate::metrics::{self, Metrics};
+use crate::output::OutputFormat;
+use crate::progress::{ProgressObserver, TileProgress};
+use crate::scene::Scene;
+use crate::tile::{tiles, TileOrder};
+use crate::tonemap::ToneMap;
 use crate::vector::Vec3;
 use crate::{camera::Camera, grid::Grid};
-use rand::{thread_rng, Rng};
-use std::path::Path;
-use std::time;
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{self, Duration};
 
 pub struct Renderer {
-    pub filename: &'static str,
-    pub output_dir: &'static str,
+    pub filename: String,
+    pub output_dir: String,
     pub camera: Camera,
     /// Number of samples to use for antialiasing.
     pub samples: usize,
+    /// Rendering the same scene with the same seed always gives exactly the same image.
+    pub seed: u64,
+    /// How to display radiance brighter than 1 when saving 8-bit images.
+    pub tone_map: ToneMap,
+    /// How to encode linear radiance when saving 8-bit images.
+    pub transfer: TransferFunction,
+    /// If set, render in several passes and save the partial image as it goes.
+    pub progressive: Option<Progressive>,
+    /// The image is rendered in square tiles, this many pixels wide.
+    pub tile_size: usize,
+    /// Which order the tiles are rendered in.
+    pub tile_order: TileOrder,
+}
+
+/// Settings for rendering an image in several passes, instead of all at once. Each pass adds more
+/// samples to every pixel, and the partial image is saved between passes, so you can watch it get
+/// less noisy and stop the render once it looks good enough.
+#[derive(Clone, Copy, Debug)]
+pub struct Progressive {
+    /// How many samples each pass adds to every pixel.
+    pub samples_per_pass: usize,
+    /// Don't save the partial image more often than this.
+    pub save_interval: Duration,
 }
 
 impl Renderer {
-    /// Computes the image and then writes it to the filesystem as a .png image.
-    /// The `color_hit_by` arg computes the color of the object the ray hits.
-    pub fn render_img<F, const W: usize, const H: usize>(
+    /// Computes the image and then writes it to the filesystem, in whichever image format
+    /// `filename`'s extension says. HDR formats (.exr and .hdr) keep the linear radiance of each
+    /// pixel, other formats are tone mapped and encoded down to 8 bits per channel.
+    /// The `integrator` computes what each ray from the camera sees.
+    pub fn render_img<I: Integrator>(
+        &self,
+        scene: Scene,
+        integrator: &I,
+        pixels: Grid<Vec3>,
+    ) -> Result<Metrics, RenderError> {
+        self.render_img_with_progress(scene, integrator, pixels, &())
+    }
+
+    /// Like `render_img`, but tells the observer about each tile as it's finished.
+    pub fn render_img_with_progress<I: Integrator>(
+        &self,
+        scene: Scene,
+        integrator: &I,
+        mut pixels: Grid<Vec3>,
+        observer: &dyn ProgressObserver,
+    ) -> Result<Metrics, RenderError> {
+        // Check everything before rendering, so we don't spend ages rendering an image that can't
+        // be saved.
+        if self.samples == 0 {
+            return Err(RenderError::InvalidParameter(
+                "samples must be at least 1".to_owned(),
+            ));
+        }
+        if pixels.size() == 0 {
+            return Err(RenderError::InvalidParameter(format!(
+                "image must be at least 1x1 pixels, not {}x{}",
+                pixels.width(),
+                pixels.height()
+            )));
+        }
+        let samples_per_pass = match self.progressive {
+            Some(Progressive {
+                samples_per_pass: 0,
+                ..
+            }) => {
+                return Err(RenderError::InvalidParameter(
+                    "progressive rendering needs at least 1 sample per pass".to_owned(),
+                ))
+            }
+            Some(progressive) => progressive.samples_per_pass,
+            None => self.samples,
+        };
+        if self.tile_size == 0 {
+            return Err(RenderError::InvalidParameter(
+                "tiles must be at least 1 pixel wide".to_owned(),
+            ));
+        }
+        let (path, format) = self.output_path()?;
+
+        // Start every thread's counters from zero, so this render's metrics only count this render.
+        metrics::reset_thread_counters();
+        rayon_core::broadcast(|_| metrics::reset_thread_counters());
+        let metrics = Mutex::new(Metrics::new(self.samples * pixels.size()));
+        let (height, width) = (pixels.height(), pixels.width());
+        let tiles = tiles(width, height, self.tile_size, self.tile_order);
+        let passes = self.samples.div_ceil(samples_per_pass);
+        observer.started(tiles.len() * passes, self.samples * pixels.size());
+        let mut samples_done = 0;
+        let mut last_save = time::Instant::now();
+        for pass in 0.. {
+            let samples = samples_per_pass.min(self.samples - samples_done);
+            let render_fn = self.render(&scene, integrator, height, width, pass, samples);
+            let start = time::Instant::now();
+            // Each pixel holds the average of every sample so far, so mix this pass's average in,
+            // weighted by how many samples it's made of.
+            pixels.update_tiles_parallel(
+                &tiles,
+                |p, avg| {
+                    let start = time::Instant::now();
+                    let total = *avg * samples_done as f64 + render_fn(p) * samples as f64;
+                    *avg = total / (samples_done + samples) as f64;
+                    metrics::record_busy_time(start.elapsed());
+                },
+                |tile, pixels| {
+                    // This runs on the thread which rendered the tile, so it can hand over
+                    // that thread's counters.
+                    metrics.lock().unwrap().collect_thread_counters();
+                    observer.tile_done(&TileProgress {
+                        tile,
+                        pixels,
+                        samples,
+                    })
+                },
+            );
+            metrics.lock().unwrap().time_spent += start.elapsed();
+            samples_done += samples;
+
+            if samples_done == self.samples {
+                break;
+            }
+            if let Some(progressive) = self.progressive {
+                if last_save.elapsed() >= progressive.save_interval {
+                    self.save(&pixels, &path, format)?;
+                    last_save = time::Instant::now();
+                }
+            }
+        }
+        observer.finished();
+        self.save(&pixels, &path, format)?;
+        Ok(metrics.into_inner().unwrap())
+    }
+
+    /// Where the image will be written. Fails if we don't know how to write that kind of image, or
+    /// the output directory doesn't exist, so that we find out before rendering rather than after.
+    fn output_path(&self) -> Result<(PathBuf, OutputFormat), RenderError> {
+        let dir = Path::new(&self.output_dir);
+        // An empty directory means the current one.
+        if !dir.as_os_str().is_empty() && !dir.is_dir() {
+            return Err(RenderError::MissingOutputDir(dir.to_owned()));
+        }
+        let path = dir.join(&self.filename);
+        match OutputFormat::from_path(&path) {
+            Some(format) => Ok((path, format)),
+            None => Err(RenderError::UnsupportedExtension(self.filename.clone())),
+        }
+    }
+
+    /// Write the image to a temporary file, then move it into place. That way the file at `path`
+    /// is always a complete image, even if the render is stopped while it's being saved.
+    fn save(
         &self,
-        scene: Hittable,
-        color_hit_by: F,
-        mut pixels: Grid<[u8; 3], W, H>,
-    ) -> Metrics
-    where
-        F: Sync + Send + Fn(&Ray, &Hittable, u8) -> Color,
-    {
-        let mut metrics = Metrics::new(self.samples * pixels.size());
-        let render_fn = self.render(scene, color_hit_by, pixels.height(), pixels.width());
-        let start = time::Instant::now();
-        pixels.set_all_parallel(render_fn);
-        metrics.time_spent = start.elapsed();
-        self.output_img(pixels);
-        metrics
+        pixels: &Grid<Vec3>,
+        path: &Path,
+        format: OutputFormat,
+    ) -> Result<(), RenderError> {
+        let mut partial_path = path.as_os_str().to_owned();
+        partial_path.push(".partial");
+        let partial_path = PathBuf::from(partial_path);
+        format.write(pixels, &partial_path, self.tone_map, self.transfer)?;
+        std::fs::rename(&partial_path, path)?;
+        Ok(())
     }
 
-    /// Returns a closure which computes the colour of each pixel in the image.
-    /// `scene` is a composition of all objects in the scene.
-    /// `color_hit_by` computes the color of whichever object the ray hits.
-    pub fn render<F>(
+    /// Returns a closure which computes the linear radiance of each pixel in the image, averaged
+    /// over `samples` samples. Each pass gets different random samples.
+    /// `scene` holds all the objects in the scene.
+    /// `integrator` computes what each ray from the camera sees.
+    pub fn render<'a, I: Integrator>(
         &self,
-        scene: Hittable,
-        color_hit_by: F,
+        scene: &'a Scene,
+        integrator: &'a I,
         height: usize,
         width: usize,
-    ) -> impl Send + Sync + Fn(Point) -> [u8; 3]
-    where
-        F: Sync + Send + Fn(&Ray, &Hittable, u8) -> Color,
-    {
-        let samples = self.samples;
+        pass: u64,
+        samples: usize,
+    ) -> impl Send + Sync + Fn(Point) -> Vec3 + 'a {
         let camera = self.camera;
+        let seed = self.seed;
         move |Point { x, y }| {
-            let mut rng = thread_rng();
+            let mut rng = pixel_rng(seed, x, y, pass);
             let dy = (height - y) as f64;
 
             // Sample a number of points inside the pixel, get each of their colors, and average them
             // all together. This is called "antialiasing" and helps the image look smoother.
-            let sample_rays = (0..samples).into_iter().map(|_| {
+            let sample_rays = (0..samples).map(|_| {
                 // Choose a random point inside this pixel
                 let u = (x as f64 + rng.gen::<f64>()) / width as f64;
                 let v = (dy + rng.gen::<f64>()) / height as f64;
 
                 // Then get the ray from the camera to that point,
                 // check what color it hits.
-                let ray = camera.ray_to_point(u, v);
-                let color_at_this_point = color_hit_by(&ray, &scene, 0);
+                let ray = camera.ray_to_point(u, v, &mut rng);
+                let color_at_this_point = integrator.radiance(&ray, scene, &mut rng);
 
-                color_at_this_point.vec()
+                // A single NaN or infinite sample would ruin the whole pixel, so drop it.
+                if color_at_this_point.is_finite() {
+                    color_at_this_point
+                } else {
+                    Vec3::default()
+                }
             });
 
             // Average the colour of all the points sampled from inside the pixel
-            let avg_color = sample_rays.sum::<Vec3>().scale(1.0 / samples as f64);
-            Color::from(avg_color).to_rgb_gamma_corrected()
+            sample_rays.sum::<Vec3>().scale(1.0 / samples as f64)
         }
     }
-
-    /// Convert the pixel array to a JPG and write it to the local filesystem.
-    fn output_img<const W: usize, const H: usize>(&self, pixels: Grid<[u8; 3], W, H>) {
-        let mut img_buf = image::ImageBuffer::new(pixels.width() as u32, pixels.height() as u32);
-        for (x, y, pixel) in img_buf.enumerate_pixels_mut() {
-            let color = pixels.get(&Point {
-                x: x as usize,
-                y: y as usize,
-            });
-            *pixel = image::Rgb(*color);
-        }
-        // Write the image to disk
-        let path = Path::new(self.output_dir).join(self.filename);
-        img_buf.save(path).unwrap();
-    }
 }
 
-pub fn color_hit_by(ray: &Ray, scene: &Hittable, depth: u8) -> Color {
-    // What color should this pixel be?
-    // If the ray hits an object:
-    if let Some(hit) = scene.hit(&ray, 0.001, std::f64::MAX) {
-        // It should reflect off that object, and we can calculate that reflection's colour recursively.
-        // I tried converting this to an iteration or a tail-recursion; neither affected performance,
-        // so I stuck with the plain old recursion, because I thought it was more readable.
-
-        if depth < 50 {
-            if let Some(scatter) = hit.material.scatter(&ray, &hit) {
-                Color::from(
-                    color_hit_by(&scatter.scattered, &scene, depth + 1).vec() * scatter.attenuation,
-                )
-            } else {
-                Color::new_uniform(0.0)
-            }
-        } else {
-            Color::new_uniform(0.0)
-        }
-
-    // Otherwise, it'll be the color of the background.
-    } else {
-        background(ray)
-    }
+/// Each pixel gets its own random number generator for each pass, seeded from the renderer's seed
+/// and the pixel's position. That way the image doesn't depend on which thread rendered which pixel.
+fn pixel_rng(seed: u64, x: usize, y: usize, pass: u64) -> Xoshiro256PlusPlus {
+    let pixel = ((y as u64) << 32) | x as u64;
+    Xoshiro256PlusPlus::seed_from_u64(splitmix64(splitmix64(seed ^ splitmix64(pixel)) ^ pass))
 }
 
-/// Render the nice blue/white background
-fn background(r: &Ray) -> Color {
-    let t = r.direction.unit().y * 0.5 + 1.0;
-    let white = Color::new_uniform(1.0);
-    let blue = Color::new(0.8, 1.0, 1.0);
-    white.vec().interpolate(&blue.vec(), t).into()
+/// Scrambles the bits of a number, so that similar inputs (like neighbouring pixels, or seeds
+/// 1 and 2) give very different outputs.
+fn splitmix64(z: u64) -> u64 {
+    let z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
@@ -0,0 +1,196 @@
+use crate::color::Color;
+use crate::hittable::{intersect_triangle, Hit, Hittable, Sphere};
+use crate::light::Light;
+use crate::ray::Ray;
+use crate::vector::Vec3;
+use rand::Rng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use std::f64::consts::PI;
+
+/// Everything that gets rendered: the objects, plus a list of the ones which give off light, so
+/// integrators can send rays straight at them instead of waiting to hit them by chance.
+pub struct Scene {
+    pub world: Hittable,
+    /// Every emissive sphere and triangle in `world`, including the triangles of emissive meshes.
+    pub emitters: Vec<Emitter>,
+    /// Lights which aren't part of `world`.
+    pub lights: Vec<Light>,
+    /// What rays see if they escape the scene.
+    pub background: Background,
+}
+
+/// Part of `world` which gives off light, in a shape rays can be aimed at.
+#[derive(Clone, Copy)]
+pub enum Emitter {
+    Sphere(Sphere),
+    /// The corners of a triangle, on its own or from a mesh. Gives off light from both sides.
+    Triangle([Vec3; 3]),
+}
+
+impl Emitter {
+    /// A triangle emitter, or None if the triangle has no area (there's nothing to aim at).
+    pub(crate) fn triangle(vertices: [Vec3; 3]) -> Option<Self> {
+        let [a, b, c] = vertices;
+        if (b - a).cross(c - a).length() > 0.0 {
+            Some(Self::Triangle(vertices))
+        } else {
+            None
+        }
+    }
+
+    /// A random direction from `origin` towards the emitter, or None if `origin` is inside it.
+    fn sample_towards(&self, origin: Vec3, rng: &mut Xoshiro256PlusPlus) -> Option<Vec3> {
+        match self {
+            Self::Sphere(sphere) => sample_cone_towards(sphere, origin, rng),
+            Self::Triangle([a, b, c]) => {
+                // A uniformly random point on the triangle. Without the square root, points would
+                // bunch up around `a`.
+                let r1 = rng.gen::<f64>().sqrt();
+                let r2 = rng.gen::<f64>();
+                let point = *a * (1.0 - r1) + *b * (r1 * (1.0 - r2)) + *c * (r1 * r2);
+                Some(point - origin)
+            }
+        }
+    }
+
+    /// The probability density (per unit solid angle) of `sample_towards` picking the ray's
+    /// direction, or None if the ray misses the emitter (or can't be sampled from there).
+    fn pdf(&self, ray: &Ray) -> Option<f64> {
+        match self {
+            Self::Sphere(sphere) => {
+                sphere.hit(ray, 0.001, f64::MAX)?;
+                let cos_theta_max = cone_cos_theta_max(sphere, ray.origin)?;
+                Some(1.0 / (2.0 * PI * (1.0 - cos_theta_max)))
+            }
+            Self::Triangle(vertices) => {
+                // Points are picked uniformly by area, so convert from per unit area to per unit
+                // solid angle: further away, and more side-on, triangles cover less of the view.
+                let (t, _, _) = intersect_triangle(ray, vertices, 0.001, f64::MAX)?;
+                let [a, b, c] = vertices;
+                let normal = (*b - *a).cross(*c - *a);
+                let area = normal.length() / 2.0;
+                let cosine = normal.unit().dot(&ray.direction.unit()).abs();
+                let distance_squared = (ray.direction * t).squared_length();
+                if cosine <= 0.0 {
+                    return None;
+                }
+                Some(distance_squared / (area * cosine))
+            }
+        }
+    }
+}
+
+/// What a ray sees if it escapes the scene without hitting anything.
+#[derive(Clone, Copy, Default)]
+pub enum Background {
+    /// The nice blue/white sky gradient, which lights up the whole scene.
+    #[default]
+    Sky,
+    /// A single flat color, e.g. black for indoor scenes lit only by emissive objects.
+    Solid(Color),
+}
+
+impl Background {
+    pub(crate) fn color(&self, ray: &Ray) -> Color {
+        match self {
+            Self::Sky => sky(ray),
+            Self::Solid(color) => *color,
+        }
+    }
+}
+
+/// Render the nice blue/white background
+fn sky(r: &Ray) -> Color {
+    let t = r.direction.unit().y * 0.5 + 1.0;
+    let white = Color::new_uniform(1.0);
+    let blue = Color::new(0.8, 1.0, 1.0);
+    white.vec().interpolate(&blue.vec(), t).into()
+}
+
+impl Scene {
+    pub fn new(world: Hittable) -> Self {
+        Self::with_lights(world, Vec::new())
+    }
+
+    pub fn with_lights(world: Hittable, lights: Vec<Light>) -> Self {
+        let emitters = world.emitters();
+        Scene {
+            world,
+            emitters,
+            lights,
+            background: Background::default(),
+        }
+    }
+
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+        self.world.hit(ray, t_min, t_max)
+    }
+
+    /// Picks a random emitter, and a random direction from `origin` towards it. Returns the
+    /// direction, and its probability density (per unit solid angle) under `emitter_pdf`.
+    /// Returns None if there are no emitters, or `origin` is inside the one it picked.
+    pub fn sample_emitter(
+        &self,
+        origin: Vec3,
+        rng: &mut Xoshiro256PlusPlus,
+    ) -> Option<(Vec3, f64)> {
+        if self.emitters.is_empty() {
+            return None;
+        }
+        let emitter = &self.emitters[rng.gen_range(0..self.emitters.len())];
+        let direction = emitter.sample_towards(origin, rng)?;
+        Some((direction, self.emitter_pdf(origin, direction)))
+    }
+
+    /// The probability density (per unit solid angle) of `sample_emitter` choosing `direction`
+    /// from `origin`. Emitters can overlap from where `origin` is, so this adds up the chance of
+    /// every emitter being picked and then sampled in that direction.
+    pub fn emitter_pdf(&self, origin: Vec3, direction: Vec3) -> f64 {
+        if self.emitters.is_empty() {
+            return 0.0;
+        }
+        let ray = Ray { origin, direction };
+        let total: f64 = self
+            .emitters
+            .iter()
+            .filter_map(|emitter| emitter.pdf(&ray))
+            .sum();
+        total / self.emitters.len() as f64
+    }
+}
+
+/// From outside a sphere, the sphere covers a cone of directions. This is the cosine of the angle
+/// between the cone's axis and its edge, or None if `origin` is inside the sphere.
+fn cone_cos_theta_max(sphere: &Sphere, origin: Vec3) -> Option<f64> {
+    let distance_squared = (sphere.center - origin).squared_length();
+    let radius_squared = sphere.radius * sphere.radius;
+    if distance_squared <= radius_squared {
+        return None;
+    }
+    Some((1.0 - radius_squared / distance_squared).sqrt())
+}
+
+/// A random direction from `origin` which hits the sphere, picked uniformly from the cone of
+/// directions the sphere covers. Much better than picking a random point on the sphere's surface,
+/// because half of those points are on the far side, hidden from `origin`.
+fn sample_cone_towards(
+    sphere: &Sphere,
+    origin: Vec3,
+    rng: &mut Xoshiro256PlusPlus,
+) -> Option<Vec3> {
+    let cos_theta_max = cone_cos_theta_max(sphere, origin)?;
+    let cos_theta = 1.0 + rng.gen::<f64>() * (cos_theta_max - 1.0);
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+    let phi = 2.0 * PI * rng.gen::<f64>();
+
+    // Build a set of axes around the direction to the sphere's center, to point the cone along.
+    let w = (sphere.center - origin).unit();
+    let a = if w.x.abs() > 0.9 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let v = w.cross(a).unit();
+    let u = w.cross(v);
+    Some(u * (phi.cos() * sin_theta) + v * (phi.sin() * sin_theta) + w * cos_theta)
+}
@@ -0,0 +1,812 @@
+//! Scenes can be described in TOML files, so they can be changed without recompiling. For example:
+//!
+//! ```toml
+//! [renderer]
+//! samples = 100
+//! width = 800
+//! height = 400
+//! filename = "spheres.png"
+//!
+//! [integrator]
+//! type = "path"
+//! max_depth = 50
+//!
+//! [camera]
+//! look_from = [0.0, 0.0, 0.0]
+//! look_at = [0.0, 0.0, -1.0]
+//! vfov = 90.0
+//!
+//! [[objects]]
+//! type = "sphere"
+//! center = [0.0, 0.0, -1.0]
+//! radius = 0.5
+//! material = { type = "diffuse", albedo = [0.8, 0.3, 0.8] }
+//!
+//! [[lights]]
+//! type = "point"
+//! position = [0.0, 2.0, 0.0]
+//! intensity = [10.0, 10.0, 10.0]
+//! ```
+use crate::camera::Camera;
+use crate::color::{Color, TransferFunction};
+use crate::gltf_import::{self, GltfCamera, GltfError};
+use crate::hittable::{Hittable, Sphere, Triangle};
+use crate::integrator::{
+    AmbientOcclusion, AnyIntegrator, Depth, Normals, PathSettings, PathTracer, RussianRoulette,
+};
+use crate::light::Light;
+use crate::material::Material;
+use crate::mesh::{Mesh, MeshData};
+use crate::obj::{self, ObjError};
+use crate::render::{Progressive, Renderer};
+use crate::scene::{Background, Scene};
+use crate::tile::TileOrder;
+use crate::tonemap::ToneMap;
+use crate::vector::Vec3;
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Everything needed to render the scene described by a scene file.
+pub struct Setup {
+    pub renderer: Renderer,
+    pub integrator: AnyIntegrator,
+    pub scene: Scene,
+    /// Image width in pixels
+    pub width: usize,
+    /// Image height in pixels
+    pub height: usize,
+}
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    /// The file couldn't be read.
+    Io(PathBuf, std::io::Error),
+    /// The file isn't valid TOML, or is missing fields, or has fields of the wrong type.
+    Parse(toml::de::Error),
+    /// A field's value doesn't make sense, e.g. a metal with negative fuzz.
+    Invalid { field: String, reason: String },
+    /// A model file the scene uses couldn't be loaded.
+    Model { field: String, err: ObjError },
+    /// A glTF scene the scene file uses couldn't be loaded.
+    Gltf { field: String, err: GltfError },
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "couldn't read {}: {}", path.display(), err),
+            Self::Parse(err) => write!(f, "{}", err),
+            Self::Invalid { field, reason } => write!(f, "invalid `{}`: {}", field, reason),
+            Self::Model { field, err } => write!(f, "couldn't load `{}`: {}", field, err),
+            Self::Gltf { field, err } => write!(f, "couldn't load `{}`: {}", field, err),
+        }
+    }
+}
+
+impl std::error::Error for SceneFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, err) => Some(err),
+            Self::Parse(err) => Some(err),
+            Self::Invalid { .. } => None,
+            Self::Model { err, .. } => Some(err),
+            Self::Gltf { err, .. } => Some(err),
+        }
+    }
+}
+
+/// Read and parse the scene file at the given path. Model files are looked for relative to the
+/// scene file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Setup, SceneFileError> {
+    let path = path.as_ref();
+    let contents =
+        std::fs::read_to_string(path).map_err(|err| SceneFileError::Io(path.to_owned(), err))?;
+    parse_in_dir(&contents, path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+/// Parse the contents of a scene file. Model files are looked for relative to the current
+/// directory.
+pub fn parse(contents: &str) -> Result<Setup, SceneFileError> {
+    parse_in_dir(contents, Path::new(""))
+}
+
+fn parse_in_dir(contents: &str, dir: &Path) -> Result<Setup, SceneFileError> {
+    let file: SceneFile = toml::from_str(contents).map_err(SceneFileError::Parse)?;
+    file.build(dir)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    renderer: RendererSection,
+    integrator: Option<IntegratorSection>,
+    /// Can be left out if a glTF object has a camera.
+    camera: Option<CameraSection>,
+    #[serde(default)]
+    objects: Vec<ObjectSection>,
+    #[serde(default)]
+    lights: Vec<LightSection>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RendererSection {
+    samples: usize,
+    width: usize,
+    height: usize,
+    filename: String,
+    #[serde(default = "default_output_dir")]
+    output_dir: String,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    background: BackgroundSection,
+    #[serde(default)]
+    tone_map: ToneMapSection,
+    #[serde(default)]
+    transfer: TransferSection,
+    progressive: Option<ProgressiveSection>,
+    #[serde(default = "default_tile_size")]
+    tile_size: usize,
+    #[serde(default)]
+    tile_order: TileOrderSection,
+    /// Where the path tracer's settings went before there was an `[integrator]` section. Still
+    /// read, so older scene files keep working.
+    max_depth: Option<usize>,
+    russian_roulette: Option<RussianRouletteSection>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum IntegratorSection {
+    Path {
+        #[serde(default = "default_max_depth")]
+        max_depth: usize,
+        russian_roulette: Option<RussianRouletteSection>,
+        #[serde(default = "default_sample_lights")]
+        sample_lights: bool,
+    },
+    Normals,
+    Depth {
+        max_distance: f64,
+    },
+    AmbientOcclusion {
+        #[serde(default = "default_ao_samples")]
+        samples: usize,
+        distance: f64,
+    },
+}
+
+fn default_sample_lights() -> bool {
+    PathTracer::default().sample_lights
+}
+
+fn default_max_depth() -> usize {
+    PathSettings::default().max_depth
+}
+
+fn default_ao_samples() -> usize {
+    16
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RussianRouletteSection {
+    #[serde(default = "default_roulette_start_depth")]
+    start_depth: usize,
+}
+
+fn default_roulette_start_depth() -> usize {
+    3
+}
+
+fn default_tile_size() -> usize {
+    32
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+enum TileOrderSection {
+    Scanline,
+    #[default]
+    Spiral,
+    Hilbert,
+}
+
+fn default_output_dir() -> String {
+    "output".to_owned()
+}
+
+#[derive(Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BackgroundSection {
+    #[default]
+    Sky,
+    Solid {
+        color: [f64; 3],
+    },
+}
+
+#[derive(Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ToneMapSection {
+    #[default]
+    Clamp,
+    Reinhard,
+    AcesFilmic,
+    Exposure {
+        exposure: f64,
+    },
+}
+
+#[derive(Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TransferSection {
+    #[default]
+    Srgb,
+    Gamma {
+        gamma: f64,
+    },
+    Linear,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProgressiveSection {
+    samples_per_pass: usize,
+    #[serde(default = "default_save_interval_secs")]
+    save_interval_secs: f64,
+}
+
+fn default_save_interval_secs() -> f64 {
+    10.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraSection {
+    look_from: [f64; 3],
+    look_at: [f64; 3],
+    #[serde(default = "default_view_up")]
+    view_up: [f64; 3],
+    vfov: f64,
+    /// Defaults to the image's width / height.
+    aspect_ratio: Option<f64>,
+    #[serde(default)]
+    aperture: f64,
+    /// Defaults to the distance between `look_from` and `look_at`.
+    focus_dist: Option<f64>,
+}
+
+fn default_view_up() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectSection {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: MaterialSection,
+    },
+    Triangle {
+        vertices: [[f64; 3]; 3],
+        material: MaterialSection,
+    },
+    /// Small meshes can be written out in the scene file, bigger ones should be loaded from files.
+    Mesh {
+        positions: Vec<[f64; 3]>,
+        normals: Option<Vec<[f64; 3]>>,
+        uvs: Option<Vec<[f64; 2]>>,
+        triangles: Vec<[usize; 3]>,
+        material: MaterialSection,
+    },
+    /// A Wavefront OBJ model, using the materials from its MTL files unless `material` is given.
+    Obj {
+        path: PathBuf,
+        material: Option<MaterialSection>,
+    },
+    /// A glTF scene, using its own materials unless `material` is given. Its lights are added to
+    /// the scene's, and its camera is used if the scene file doesn't have one.
+    Gltf {
+        path: PathBuf,
+        material: Option<MaterialSection>,
+    },
+}
+
+/// What an `ObjectSection` adds to the scene. Usually just something to hit, but glTF scenes can
+/// bring their own lights and camera too.
+struct Object {
+    hittable: Hittable,
+    lights: Vec<Light>,
+    camera: Option<GltfCamera>,
+}
+
+impl From<Hittable> for Object {
+    fn from(hittable: Hittable) -> Self {
+        Object {
+            hittable,
+            lights: Vec::new(),
+            camera: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum LightSection {
+    Point {
+        position: [f64; 3],
+        intensity: [f64; 3],
+    },
+    Spot {
+        position: [f64; 3],
+        direction: [f64; 3],
+        intensity: [f64; 3],
+        cone_angle: f64,
+        /// Defaults to `cone_angle`, i.e. a hard edge.
+        falloff_start: Option<f64>,
+    },
+    Directional {
+        direction: [f64; 3],
+        irradiance: [f64; 3],
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialSection {
+    Diffuse { albedo: [f64; 3] },
+    Metal { albedo: [f64; 3], fuzz: f64 },
+    Dielectric { refraction_index: f64 },
+    Emissive { radiance: [f64; 3] },
+}
+
+impl SceneFile {
+    fn build(self, dir: &Path) -> Result<Setup, SceneFileError> {
+        let background = self.renderer.background.build("renderer.background")?;
+        ensure(
+            self.renderer.samples > 0,
+            "renderer.samples",
+            "must be at least 1",
+        )?;
+        ensure(
+            self.renderer.width > 0,
+            "renderer.width",
+            "must be at least 1",
+        )?;
+        ensure(
+            self.renderer.height > 0,
+            "renderer.height",
+            "must be at least 1",
+        )?;
+        ensure(
+            self.renderer.tile_size > 0,
+            "renderer.tile_size",
+            "must be at least 1",
+        )?;
+        let mut objects = Vec::new();
+        let mut lights = Vec::new();
+        let mut gltf_camera = None;
+        for (i, object) in self.objects.into_iter().enumerate() {
+            let object = object.build(&format!("objects[{}]", i), dir)?;
+            objects.push(object.hittable);
+            lights.extend(object.lights);
+            gltf_camera = gltf_camera.or(object.camera);
+        }
+        for (i, light) in self.lights.into_iter().enumerate() {
+            lights.push(light.build(&format!("lights[{}]", i))?);
+        }
+
+        let image_aspect_ratio = self.renderer.width as f64 / self.renderer.height as f64;
+        let camera = match (self.camera, gltf_camera) {
+            (Some(camera), _) => camera.build("camera", image_aspect_ratio)?,
+            (None, Some(camera)) => camera.build(image_aspect_ratio),
+            (None, None) => {
+                return Err(SceneFileError::Invalid {
+                    field: "camera".to_owned(),
+                    reason: "missing, and no glTF object has a camera".to_owned(),
+                })
+            }
+        };
+        let renderer = Renderer {
+            filename: self.renderer.filename,
+            output_dir: self.renderer.output_dir,
+            camera,
+            samples: self.renderer.samples,
+            seed: self.renderer.seed,
+            tone_map: self.renderer.tone_map.build("renderer.tone_map")?,
+            transfer: self.renderer.transfer.build("renderer.transfer")?,
+            progressive: self
+                .renderer
+                .progressive
+                .map(|progressive| progressive.build("renderer.progressive"))
+                .transpose()?,
+            tile_size: self.renderer.tile_size,
+            tile_order: match self.renderer.tile_order {
+                TileOrderSection::Scanline => TileOrder::Scanline,
+                TileOrderSection::Spiral => TileOrder::Spiral,
+                TileOrderSection::Hilbert => TileOrder::Hilbert,
+            },
+        };
+        let integrator = match self.integrator {
+            Some(integrator) => {
+                ensure(
+                    self.renderer.max_depth.is_none() && self.renderer.russian_roulette.is_none(),
+                    "renderer",
+                    "max_depth and russian_roulette go in the [integrator] section when there is one",
+                )?;
+                integrator.build("integrator")?
+            }
+            None => IntegratorSection::Path {
+                max_depth: self.renderer.max_depth.unwrap_or_else(default_max_depth),
+                russian_roulette: self.renderer.russian_roulette,
+                sample_lights: default_sample_lights(),
+            }
+            .build("renderer")?,
+        };
+        let mut scene = Scene::with_lights(Hittable::bvh(objects), lights);
+        scene.background = background;
+        Ok(Setup {
+            renderer,
+            integrator,
+            scene,
+            width: self.renderer.width,
+            height: self.renderer.height,
+        })
+    }
+}
+
+impl IntegratorSection {
+    fn build(self, field: &str) -> Result<AnyIntegrator, SceneFileError> {
+        match self {
+            Self::Path {
+                max_depth,
+                russian_roulette,
+                sample_lights,
+            } => Ok(AnyIntegrator::Path(PathTracer {
+                path: PathSettings {
+                    max_depth,
+                    russian_roulette: russian_roulette.map(|roulette| RussianRoulette {
+                        start_depth: roulette.start_depth,
+                    }),
+                },
+                sample_lights,
+            })),
+            Self::Normals => Ok(AnyIntegrator::Normals(Normals)),
+            Self::Depth { max_distance } => {
+                ensure(
+                    max_distance > 0.0,
+                    &format!("{}.max_distance", field),
+                    "must be positive",
+                )?;
+                Ok(AnyIntegrator::Depth(Depth { max_distance }))
+            }
+            Self::AmbientOcclusion { samples, distance } => {
+                ensure(
+                    samples > 0,
+                    &format!("{}.samples", field),
+                    "must be at least 1",
+                )?;
+                ensure(
+                    distance > 0.0,
+                    &format!("{}.distance", field),
+                    "must be positive",
+                )?;
+                Ok(AnyIntegrator::AmbientOcclusion(AmbientOcclusion {
+                    samples,
+                    distance,
+                }))
+            }
+        }
+    }
+}
+
+impl BackgroundSection {
+    fn build(self, field: &str) -> Result<Background, SceneFileError> {
+        match self {
+            Self::Sky => Ok(Background::Sky),
+            Self::Solid { color } => {
+                let color = unit_interval(color, &format!("{}.color", field))?;
+                Ok(Background::Solid(Color::from(color)))
+            }
+        }
+    }
+}
+
+impl ToneMapSection {
+    fn build(self, field: &str) -> Result<ToneMap, SceneFileError> {
+        match self {
+            Self::Clamp => Ok(ToneMap::Clamp),
+            Self::Reinhard => Ok(ToneMap::Reinhard),
+            Self::AcesFilmic => Ok(ToneMap::AcesFilmic),
+            Self::Exposure { exposure } => {
+                ensure(
+                    exposure > 0.0,
+                    &format!("{}.exposure", field),
+                    "must be positive",
+                )?;
+                Ok(ToneMap::Exposure(exposure))
+            }
+        }
+    }
+}
+
+impl TransferSection {
+    fn build(self, field: &str) -> Result<TransferFunction, SceneFileError> {
+        match self {
+            Self::Srgb => Ok(TransferFunction::Srgb),
+            Self::Gamma { gamma } => {
+                ensure(gamma > 0.0, &format!("{}.gamma", field), "must be positive")?;
+                Ok(TransferFunction::Gamma(gamma))
+            }
+            Self::Linear => Ok(TransferFunction::Linear),
+        }
+    }
+}
+
+impl ProgressiveSection {
+    fn build(self, field: &str) -> Result<Progressive, SceneFileError> {
+        ensure(
+            self.samples_per_pass > 0,
+            &format!("{}.samples_per_pass", field),
+            "must be at least 1",
+        )?;
+        let save_interval = Duration::try_from_secs_f64(self.save_interval_secs).map_err(|_| {
+            SceneFileError::Invalid {
+                field: format!("{}.save_interval_secs", field),
+                reason: "must be a positive number of seconds, and not too large".to_owned(),
+            }
+        })?;
+        Ok(Progressive {
+            samples_per_pass: self.samples_per_pass,
+            save_interval,
+        })
+    }
+}
+
+impl CameraSection {
+    fn build(self, field: &str, image_aspect_ratio: f64) -> Result<Camera, SceneFileError> {
+        let aspect_ratio = self.aspect_ratio.unwrap_or(image_aspect_ratio);
+        let look_from = vec3(self.look_from);
+        let look_at = vec3(self.look_at);
+        let view_up = vec3(self.view_up);
+        let focus_dist = self
+            .focus_dist
+            .unwrap_or_else(|| (look_from - look_at).length());
+        ensure(
+            (look_from - look_at).length() > 0.0,
+            &format!("{}.look_at", field),
+            "must be different from look_from",
+        )?;
+        ensure(
+            (look_from - look_at).cross(view_up).length() > 0.0,
+            &format!("{}.view_up", field),
+            "must not point along the line of sight",
+        )?;
+        ensure(
+            self.vfov > 0.0 && self.vfov < 180.0,
+            &format!("{}.vfov", field),
+            "must be between 0 and 180 degrees",
+        )?;
+        ensure(
+            aspect_ratio > 0.0,
+            &format!("{}.aspect_ratio", field),
+            "must be positive",
+        )?;
+        ensure(
+            self.aperture >= 0.0,
+            &format!("{}.aperture", field),
+            "can't be negative",
+        )?;
+        ensure(
+            focus_dist > 0.0,
+            &format!("{}.focus_dist", field),
+            "must be positive",
+        )?;
+        Ok(Camera::with_depth_of_field(
+            look_from,
+            look_at,
+            view_up,
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            focus_dist,
+        ))
+    }
+}
+
+impl ObjectSection {
+    fn build(self, field: &str, dir: &Path) -> Result<Object, SceneFileError> {
+        let hittable = match self {
+            Self::Sphere {
+                center,
+                radius,
+                material,
+            } => {
+                // Negative radii are allowed, they flip the normals, which makes hollow glass spheres.
+                ensure(radius != 0.0, &format!("{}.radius", field), "can't be zero")?;
+                Ok(Hittable::Sphere(Sphere {
+                    center: vec3(center),
+                    radius,
+                    material: material.build(&format!("{}.material", field))?,
+                }))
+            }
+            Self::Triangle { vertices, material } => Ok(Hittable::Triangle(Triangle {
+                vertices: vertices.map(vec3),
+                material: material.build(&format!("{}.material", field))?,
+            })),
+            Self::Mesh {
+                positions,
+                normals,
+                uvs,
+                triangles,
+                material,
+            } => {
+                let data = MeshData {
+                    positions: positions.into_iter().map(vec3).collect(),
+                    normals: normals.map(|normals| normals.into_iter().map(vec3).collect()),
+                    uvs: uvs.map(|uvs| uvs.into_iter().map(|[u, v]| (u, v)).collect()),
+                    triangles,
+                };
+                let material = material.build(&format!("{}.material", field))?;
+                let mesh =
+                    Mesh::new(Arc::new(data), material).map_err(|err| SceneFileError::Invalid {
+                        field: field.to_owned(),
+                        reason: err.to_string(),
+                    })?;
+                Ok(Hittable::Mesh(mesh))
+            }
+            Self::Obj { path, material } => {
+                let material = material
+                    .map(|material| material.build(&format!("{}.material", field)))
+                    .transpose()?;
+                obj::load(dir.join(path), material).map_err(|err| SceneFileError::Model {
+                    field: field.to_owned(),
+                    err,
+                })
+            }
+            Self::Gltf { path, material } => {
+                let material = material
+                    .map(|material| material.build(&format!("{}.material", field)))
+                    .transpose()?;
+                let scene = gltf_import::load(dir.join(path), material).map_err(|err| {
+                    SceneFileError::Gltf {
+                        field: field.to_owned(),
+                        err,
+                    }
+                })?;
+                return Ok(Object {
+                    hittable: scene.world,
+                    lights: scene.lights,
+                    camera: scene.camera,
+                });
+            }
+        };
+        hittable.map(Object::from)
+    }
+}
+
+impl MaterialSection {
+    fn build(self, field: &str) -> Result<Material, SceneFileError> {
+        match self {
+            Self::Diffuse { albedo } => Ok(Material::Diffuse {
+                albedo: unit_interval(albedo, &format!("{}.albedo", field))?,
+            }),
+            Self::Metal { albedo, fuzz } => {
+                ensure(
+                    (0.0..=1.0).contains(&fuzz),
+                    &format!("{}.fuzz", field),
+                    "must be between 0 and 1",
+                )?;
+                Ok(Material::Metal {
+                    albedo: unit_interval(albedo, &format!("{}.albedo", field))?,
+                    fuzz,
+                })
+            }
+            Self::Dielectric { refraction_index } => {
+                ensure(
+                    refraction_index > 0.0,
+                    &format!("{}.refraction_index", field),
+                    "must be positive",
+                )?;
+                Ok(Material::Dielectric { refraction_index })
+            }
+            Self::Emissive { radiance } => Ok(Material::Emissive {
+                radiance: non_negative(radiance, &format!("{}.radiance", field))?,
+            }),
+        }
+    }
+}
+
+impl LightSection {
+    fn build(self, field: &str) -> Result<Light, SceneFileError> {
+        match self {
+            Self::Point {
+                position,
+                intensity,
+            } => Ok(Light::Point {
+                position: vec3(position),
+                intensity: non_negative(intensity, &format!("{}.intensity", field))?,
+            }),
+            Self::Spot {
+                position,
+                direction,
+                intensity,
+                cone_angle,
+                falloff_start,
+            } => {
+                ensure(
+                    cone_angle > 0.0 && cone_angle <= 180.0,
+                    &format!("{}.cone_angle", field),
+                    "must be between 0 and 180 degrees",
+                )?;
+                let falloff_start = falloff_start.unwrap_or(cone_angle);
+                ensure(
+                    (0.0..=cone_angle).contains(&falloff_start),
+                    &format!("{}.falloff_start", field),
+                    "must be between 0 degrees and cone_angle",
+                )?;
+                Ok(Light::Spot {
+                    position: vec3(position),
+                    direction: direction_vec3(direction, &format!("{}.direction", field))?,
+                    intensity: non_negative(intensity, &format!("{}.intensity", field))?,
+                    cone_angle,
+                    falloff_start,
+                })
+            }
+            Self::Directional {
+                direction,
+                irradiance,
+            } => Ok(Light::Directional {
+                direction: direction_vec3(direction, &format!("{}.direction", field))?,
+                irradiance: non_negative(irradiance, &format!("{}.irradiance", field))?,
+            }),
+        }
+    }
+}
+
+fn vec3([x, y, z]: [f64; 3]) -> Vec3 {
+    Vec3::new(x, y, z)
+}
+
+/// Colors and albedos must have every component between 0 and 1.
+fn unit_interval(v: [f64; 3], field: &str) -> Result<Vec3, SceneFileError> {
+    ensure(
+        v.iter().all(|c| (0.0..=1.0).contains(c)),
+        field,
+        "components must be between 0 and 1",
+    )?;
+    Ok(vec3(v))
+}
+
+/// Amounts of light can be as bright as you like, but not negative.
+fn non_negative(v: [f64; 3], field: &str) -> Result<Vec3, SceneFileError> {
+    ensure(
+        v.iter().all(|c| *c >= 0.0),
+        field,
+        "components can't be negative",
+    )?;
+    Ok(vec3(v))
+}
+
+/// Directions can be any length, as long as they point somewhere.
+fn direction_vec3(v: [f64; 3], field: &str) -> Result<Vec3, SceneFileError> {
+    ensure(v != [0.0; 3], field, "can't be zero")?;
+    Ok(vec3(v))
+}
+
+fn ensure(condition: bool, field: &str, reason: &str) -> Result<(), SceneFileError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(SceneFileError::Invalid {
+            field: field.to_owned(),
+            reason: reason.to_owned(),
+        })
+    }
+}
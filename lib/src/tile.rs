@@ -0,0 +1,99 @@
+/// A rectangular block of pixels. Rendering an image tile by tile keeps each thread working on
+/// pixels that are close together, which see similar parts of the scene and are cheaper to cache.
+#[derive(Clone, Copy, Debug)]
+pub struct Tile {
+    /// Column of the tile's top-left pixel.
+    pub x: usize,
+    /// Row of the tile's top-left pixel.
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    /// How many pixels are in the tile?
+    pub fn size(&self) -> usize {
+        self.width * self.height
+    }
+}
+
+/// Which order to render the tiles in.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum TileOrder {
+    /// Left to right, top to bottom, like reading a book.
+    Scanline,
+    /// Start in the middle of the image (where the interesting stuff usually is), and spiral outwards.
+    #[default]
+    Spiral,
+    /// Follow a Hilbert curve, so that each tile is next to the one rendered before it.
+    Hilbert,
+}
+
+/// Split an image into tiles of `size` by `size` pixels, sorted into the given order.
+/// Tiles on the right and bottom edges might be smaller, if the image doesn't divide evenly.
+pub fn tiles(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let columns = width.div_ceil(size);
+    let rows = height.div_ceil(size);
+
+    // Tiles are generated in scanline order, paired with their column and row.
+    let mut tiles: Vec<_> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            let tile = Tile {
+                x: column * size,
+                y: row * size,
+                width: size.min(width - column * size),
+                height: size.min(height - row * size),
+            };
+            (column, row, tile)
+        })
+        .collect();
+
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            // Sort by which square "ring" around the center the tile is in, then by angle around
+            // the center, so each ring gets swept around in a circle.
+            let center_x = (columns as f64 - 1.0) / 2.0;
+            let center_y = (rows as f64 - 1.0) / 2.0;
+            let key = |column: usize, row: usize| {
+                let dx = column as f64 - center_x;
+                let dy = row as f64 - center_y;
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+            tiles.sort_by(|(c1, r1, _), (c2, r2, _)| {
+                key(*c1, *r1)
+                    .partial_cmp(&key(*c2, *r2))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+        TileOrder::Hilbert => {
+            let side = columns.max(rows).next_power_of_two();
+            tiles.sort_by_key(|(column, row, _)| hilbert_index(side, *column, *row));
+        }
+    }
+    tiles.into_iter().map(|(_, _, tile)| tile).collect()
+}
+
+/// How far along a Hilbert curve filling a `side` by `side` square is the point (x, y)?
+/// `side` must be a power of two.
+fn hilbert_index(side: usize, mut x: usize, mut y: usize) -> usize {
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as usize;
+        let ry = (y & s > 0) as usize;
+        index += s * s * ((3 * rx) ^ ry);
+        // Rotate the quadrant, so the curve inside it lines up with its neighbours.
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
@@ -0,0 +1,32 @@
+use crate::color::Color;
+use crate::vector::Vec3;
+
+/// How to squash high dynamic range radiance down into the [0, 1] range that 8-bit images can store.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ToneMap {
+    /// Anything brighter than 1 is just white.
+    #[default]
+    Clamp,
+    /// Reinhard's operator, x / (1 + x). Bright areas roll off smoothly instead of clipping.
+    Reinhard,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve. Higher contrast than Reinhard.
+    AcesFilmic,
+    /// 1 - e^(-exposure * x), like film exposed for longer or shorter. Larger is brighter.
+    Exposure(f64),
+}
+
+impl ToneMap {
+    /// Map linear radiance onto a displayable color. Each channel is mapped separately.
+    pub fn apply(&self, radiance: Vec3) -> Color {
+        let map = |x: f64| match self {
+            Self::Clamp => x,
+            Self::Reinhard => x / (1.0 + x),
+            Self::AcesFilmic => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+            Self::Exposure(exposure) => 1.0 - (-exposure * x).exp(),
+        };
+        // Negative or NaN radiance isn't physical, but floating point errors can cause it,
+        // so treat it as black.
+        let map = |x: f64| if x > 0.0 { map(x) } else { 0.0 };
+        Color::clamped(Vec3::new(map(radiance.x), map(radiance.y), map(radiance.z)))
+    }
+}
@@ -1,8 +1,8 @@
 use std::fmt;
 use std::iter::Sum;
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub, SubAssign};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -42,7 +42,7 @@ impl Vec3 {
     }
 
     pub fn refract(&self, normal: &Self, ni_over_nt: f64) -> Option<Self> {
-        let dt = self.unit().dot(&normal);
+        let dt = self.unit().dot(normal);
         let discriminant = 1.0 - ni_over_nt.powf(2.0) * (1.0 - dt.powf(2.0));
         if discriminant > 0.0 {
             let refracted =
@@ -74,6 +74,16 @@ impl Vec3 {
         (self.x * self.x) + (self.y * self.y) + (self.z * self.z)
     }
 
+    /// Are all three components finite, i.e. not NaN or infinity?
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
+    /// The largest of the three components
+    pub fn max_component(&self) -> f64 {
+        self.x.max(self.y).max(self.z)
+    }
+
     // Create a weighted average of the two vectors, i.e.
     // t*other + (1-t)self
     pub fn interpolate(&self, other: &Vec3, t: f64) -> Self {
@@ -100,7 +110,7 @@ impl<'a> Sum<&'a Vec3> for Vec3 {
     }
 }
 
-impl<'a> Sum<Vec3> for Vec3 {
+impl Sum<Vec3> for Vec3 {
     fn sum<I: Iterator<Item = Vec3>>(iter: I) -> Vec3 {
         let mut output = Vec3::zero();
         for v in iter {
@@ -110,6 +120,20 @@ impl<'a> Sum<Vec3> for Vec3 {
     }
 }
 
+/// Access the components by axis, i.e. 0 for x, 1 for y and 2 for z.
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 has no axis {}", axis),
+        }
+    }
+}
+
 impl Neg for Vec3 {
     type Output = Self;
     fn neg(self) -> Self {
@@ -0,0 +1,164 @@
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use raytracer::hittable::{Hittable, Sphere, Triangle};
+use raytracer::material::Material;
+use raytracer::mesh::{Mesh, MeshData};
+use raytracer::ray::Ray;
+use raytracer::vector::Vec3;
+use std::sync::Arc;
+
+const MATERIAL: Material = Material::Diffuse {
+    albedo: Vec3 {
+        x: 0.5,
+        y: 0.5,
+        z: 0.5,
+    },
+};
+
+/// A 6x6x6 lattice of triangles, some of them flat against each axis, which is the case where
+/// bounding boxes have no thickness.
+fn lattice_triangles() -> Vec<[Vec3; 3]> {
+    let mut triangles = Vec::new();
+    for i in 0..6 {
+        for j in 0..6 {
+            for k in 0..6 {
+                let corner = Vec3::new(i as f64, j as f64, k as f64) * 2.0;
+                let (a, b, c) = match (i + j + k) % 4 {
+                    // Flat in z, y and x.
+                    0 => (
+                        Vec3::new(0.8, 0.0, 0.0),
+                        Vec3::new(0.0, 0.8, 0.0),
+                        Vec3::zero(),
+                    ),
+                    1 => (
+                        Vec3::new(0.8, 0.0, 0.0),
+                        Vec3::new(0.0, 0.0, 0.8),
+                        Vec3::zero(),
+                    ),
+                    2 => (
+                        Vec3::new(0.0, 0.8, 0.0),
+                        Vec3::new(0.0, 0.0, 0.8),
+                        Vec3::zero(),
+                    ),
+                    // Tilted.
+                    _ => (
+                        Vec3::new(0.8, 0.2, 0.0),
+                        Vec3::new(0.0, 0.8, 0.3),
+                        Vec3::new(0.1, 0.0, 0.7),
+                    ),
+                };
+                triangles.push([corner + c, corner + a, corner + b]);
+            }
+        }
+    }
+    triangles
+}
+
+/// Spheres and triangles, both on a lattice, overlapping each other in places.
+fn lattice() -> Vec<Hittable> {
+    let mut objects = Vec::new();
+    for i in 0..6 {
+        for j in 0..6 {
+            for k in 0..6 {
+                objects.push(Hittable::Sphere(Sphere {
+                    center: Vec3::new(i as f64, j as f64, k as f64) * 2.0 + Vec3::new_uniform(1.0),
+                    radius: 0.3 + 0.1 * ((i + j + k) % 3) as f64,
+                    material: MATERIAL,
+                }));
+            }
+        }
+    }
+    objects.extend(lattice_triangles().into_iter().map(|vertices| {
+        Hittable::Triangle(Triangle {
+            vertices,
+            material: MATERIAL,
+        })
+    }));
+    objects
+}
+
+/// Rays from all around the lattice, and from inside it, towards random points in it. Some go
+/// straight along an axis, which is the hardest case for flat bounding boxes.
+fn random_rays(count: usize) -> Vec<Ray> {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(7);
+    let mut point = |from: f64, to: f64| {
+        Vec3::new(
+            rng.gen_range(from..to),
+            rng.gen_range(from..to),
+            rng.gen_range(from..to),
+        )
+    };
+    (0..count)
+        .map(|i| {
+            let origin = point(-5.0, 17.0);
+            let direction = match i % 8 {
+                0 => Vec3::new(1.0, 0.0, 0.0),
+                1 => Vec3::new(0.0, -1.0, 0.0),
+                2 => Vec3::new(0.0, 0.0, 1.0),
+                _ => point(0.0, 12.0) - origin,
+            };
+            Ray { origin, direction }
+        })
+        .collect()
+}
+
+/// Checks both find the same closest hit (or neither hits anything) for every ray. Returns how
+/// many rays hit, so tests can check they weren't all misses.
+fn assert_same_hits(expected: &Hittable, actual: &Hittable) -> usize {
+    let mut hits = 0;
+    for ray in random_rays(5000) {
+        let expected_hit = expected.hit(&ray, 0.001, f64::MAX);
+        let actual_hit = actual.hit(&ray, 0.001, f64::MAX);
+        match (expected_hit, actual_hit) {
+            (None, None) => {}
+            (Some(expected_hit), Some(actual_hit)) => {
+                hits += 1;
+                assert!(
+                    (expected_hit.t - actual_hit.t).abs() < 1e-9,
+                    "ray {:?} hit at t = {} instead of {}",
+                    ray,
+                    actual_hit.t,
+                    expected_hit.t
+                );
+            }
+            (expected_hit, actual_hit) => panic!(
+                "ray {:?} hit at {:?} instead of {:?}",
+                ray,
+                actual_hit.map(|hit| hit.t),
+                expected_hit.map(|hit| hit.t)
+            ),
+        }
+    }
+    hits
+}
+
+#[test]
+fn bvh_finds_the_same_hits_as_checking_everything() {
+    let hits = assert_same_hits(&Hittable::Many(lattice()), &Hittable::bvh(lattice()));
+    assert!(hits > 1000, "only {} rays hit anything", hits);
+}
+
+#[test]
+fn mesh_finds_the_same_hits_as_separate_triangles() {
+    let triangles = lattice_triangles();
+    let mut data = MeshData::default();
+    for vertices in &triangles {
+        let start = data.positions.len();
+        data.positions.extend(vertices);
+        data.triangles.push([start, start + 1, start + 2]);
+    }
+    let mesh = Hittable::Mesh(Mesh::new(Arc::new(data), MATERIAL).unwrap());
+    let separate = Hittable::Many(
+        triangles
+            .into_iter()
+            .map(|vertices| {
+                Hittable::Triangle(Triangle {
+                    vertices,
+                    material: MATERIAL,
+                })
+            })
+            .collect(),
+    );
+    let hits = assert_same_hits(&separate, &mesh);
+    assert!(hits > 100, "only {} rays hit anything", hits);
+}
@@ -0,0 +1,122 @@
+use raytracer::camera::Camera;
+use raytracer::color::TransferFunction;
+use raytracer::grid::Grid;
+use raytracer::hittable::{Hittable, Sphere};
+use raytracer::integrator::PathTracer;
+use raytracer::material::Material;
+use raytracer::progress::{ProgressObserver, TileProgress};
+use raytracer::render::Renderer;
+use raytracer::scene::Scene;
+use raytracer::tile::TileOrder;
+use raytracer::tonemap::ToneMap;
+use raytracer::vector::Vec3;
+use std::sync::Mutex;
+
+const WIDTH: usize = 24;
+const HEIGHT: usize = 16;
+
+/// Copies every finished tile's pixels into one image.
+struct Collect(Mutex<Vec<[f64; 3]>>);
+
+impl ProgressObserver for Collect {
+    fn tile_done(&self, progress: &TileProgress) {
+        let mut image = self.0.lock().unwrap();
+        let tile = progress.tile;
+        for (i, radiance) in progress.pixels.iter().enumerate() {
+            let (x, y) = (tile.x + i % tile.width, tile.y + i / tile.width);
+            image[y * WIDTH + x] = [radiance.x, radiance.y, radiance.z];
+        }
+    }
+}
+
+fn scene() -> Scene {
+    let sphere = |x, radius, material| {
+        Hittable::Sphere(Sphere {
+            center: Vec3::new(x, 0.0, -1.0),
+            radius,
+            material,
+        })
+    };
+    Scene::new(Hittable::bvh(vec![
+        Hittable::Sphere(Sphere {
+            center: Vec3::new(0.0, -100.5, -1.0),
+            radius: 100.0,
+            material: Material::Diffuse {
+                albedo: Vec3::new_uniform(0.5),
+            },
+        }),
+        sphere(
+            -1.0,
+            0.5,
+            Material::Dielectric {
+                refraction_index: 1.5,
+            },
+        ),
+        sphere(
+            0.0,
+            0.5,
+            Material::Diffuse {
+                albedo: Vec3::new(0.8, 0.3, 0.8),
+            },
+        ),
+        sphere(
+            1.0,
+            0.5,
+            Material::Metal {
+                albedo: Vec3::new(0.8, 0.6, 0.2),
+                fuzz: 0.3,
+            },
+        ),
+        sphere(
+            0.0,
+            0.2,
+            Material::Emissive {
+                radiance: Vec3::new_uniform(4.0),
+            },
+        ),
+    ]))
+}
+
+fn render_with_threads(threads: usize) -> Vec<[f64; 3]> {
+    let renderer = Renderer {
+        filename: format!("determinism-{}-{}.exr", std::process::id(), threads),
+        output_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+        camera: Camera::new(
+            Vec3::new(0.0, 0.5, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            60.0,
+            WIDTH as f64 / HEIGHT as f64,
+        ),
+        samples: 8,
+        seed: 42,
+        tone_map: ToneMap::default(),
+        transfer: TransferFunction::default(),
+        progressive: None,
+        tile_size: 4,
+        tile_order: TileOrder::Spiral,
+    };
+    let image = Collect(Mutex::new(vec![[0.0; 3]; WIDTH * HEIGHT]));
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap()
+        .install(|| {
+            renderer.render_img_with_progress(
+                scene(),
+                &PathTracer::default(),
+                Grid::new(WIDTH, HEIGHT),
+                &image,
+            )
+        })
+        .unwrap();
+    let _ = std::fs::remove_file(std::env::temp_dir().join(&renderer.filename));
+    image.0.into_inner().unwrap()
+}
+
+#[test]
+fn same_image_with_any_number_of_threads() {
+    let one_thread = render_with_threads(1);
+    assert!(one_thread.iter().any(|pixel| *pixel != [0.0; 3]));
+    assert_eq!(one_thread, render_with_threads(4));
+}
@@ -0,0 +1,81 @@
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use raytracer::hittable::{Hittable, Sphere, Triangle};
+use raytracer::material::Material;
+use raytracer::ray::Ray;
+use raytracer::scene::Scene;
+use raytracer::vector::Vec3;
+
+const LIGHT: Material = Material::Emissive {
+    radiance: Vec3 {
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+    },
+};
+
+/// Averages 1 / pdf over sampled directions, which comes out as the solid angle the emitters
+/// cover if the pdf is right. Also checks every sampled direction actually hits something.
+fn sampled_solid_angle(scene: &Scene, origin: Vec3) -> f64 {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(3);
+    let samples = 200_000;
+    let mut total = 0.0;
+    for _ in 0..samples {
+        let (direction, pdf) = scene.sample_emitter(origin, &mut rng).unwrap();
+        assert!(scene
+            .hit(&Ray { origin, direction }, 0.001, f64::MAX)
+            .is_some());
+        total += 1.0 / pdf;
+    }
+    total / samples as f64
+}
+
+#[test]
+fn emissive_triangles_are_sampled_by_solid_angle() {
+    // A 2x2 square, made of two triangles, 1 unit in front of the origin.
+    let (a, b, c, d) = (
+        Vec3::new(-1.0, -1.0, -1.0),
+        Vec3::new(1.0, -1.0, -1.0),
+        Vec3::new(1.0, 1.0, -1.0),
+        Vec3::new(-1.0, 1.0, -1.0),
+    );
+    let triangle = |vertices| {
+        Hittable::Triangle(Triangle {
+            vertices,
+            material: LIGHT,
+        })
+    };
+    let scene = Scene::new(Hittable::Many(vec![
+        triangle([a, b, c]),
+        triangle([a, c, d]),
+    ]));
+    assert_eq!(scene.emitters.len(), 2);
+
+    // Seen from the center of a cube, each face covers a sixth of all directions.
+    let expected = 4.0 * std::f64::consts::PI / 6.0;
+    let solid_angle = sampled_solid_angle(&scene, Vec3::zero());
+    assert!(
+        (solid_angle - expected).abs() < 0.01 * expected,
+        "expected {}, got {}",
+        expected,
+        solid_angle
+    );
+}
+
+#[test]
+fn emissive_spheres_are_sampled_by_solid_angle() {
+    let scene = Scene::new(Hittable::Sphere(Sphere {
+        center: Vec3::new(0.0, 0.0, -2.0),
+        radius: 1.0,
+        material: LIGHT,
+    }));
+    // 2π(1 - cos θ), where sin θ = radius / distance.
+    let expected = 2.0 * std::f64::consts::PI * (1.0 - 30f64.to_radians().cos());
+    let solid_angle = sampled_solid_angle(&scene, Vec3::zero());
+    assert!(
+        (solid_angle - expected).abs() < 1e-9,
+        "expected {}, got {}",
+        expected,
+        solid_angle
+    );
+}
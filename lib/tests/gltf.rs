@@ -0,0 +1,119 @@
+use raytracer::gltf_import;
+use raytracer::hittable::Hittable;
+use raytracer::light::Light;
+use raytracer::material::Material;
+use raytracer::ray::Ray;
+use raytracer::scene_file;
+use raytracer::vector::Vec3;
+
+const ROOM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../scenes/models/room.gltf");
+
+fn assert_close(actual: Vec3, expected: Vec3) {
+    assert!(
+        (actual - expected).length() < 1e-6,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// Where a ray going straight down from high above (x, z) first hits the world, and what it hit.
+fn drop_onto(world: &Hittable, x: f64, z: f64) -> (f64, Material) {
+    let ray = Ray {
+        origin: Vec3::new(x, 10.0, z),
+        direction: Vec3::new(0.0, -1.0, 0.0),
+    };
+    let hit = world
+        .hit(&ray, 0.001, f64::MAX)
+        .expect("should hit something");
+    (hit.p.y, hit.material)
+}
+
+#[test]
+fn room_nodes_are_placed_by_their_transforms() {
+    let room = gltf_import::load(ROOM, None).unwrap();
+
+    // Each cube is a unit cube, moved (and rotated or scaled) into place by its node.
+    let (top, material) = drop_onto(&room.world, -1.3, 0.0);
+    assert!((top - 1.0).abs() < 1e-6, "red cube top at {}", top);
+    assert!(matches!(material, Material::Diffuse { albedo } if albedo.x > albedo.y));
+
+    let (top, material) = drop_onto(&room.world, 1.3, 0.0);
+    assert!((top - 0.8).abs() < 1e-6, "gold cube top at {}", top);
+    assert!(matches!(material, Material::Metal { .. }));
+
+    let (top, material) = drop_onto(&room.world, 0.0, 1.2);
+    assert!((top - 1.2).abs() < 1e-6, "glass cube top at {}", top);
+    assert!(
+        matches!(material, Material::Dielectric { refraction_index } if refraction_index == 1.5)
+    );
+
+    let (floor, material) = drop_onto(&room.world, 3.0, -3.0);
+    assert!(floor.abs() < 1e-6, "floor at {}", floor);
+    assert!(matches!(material, Material::Diffuse { .. }));
+
+    // The lamp hangs from the ceiling, facing down.
+    let ray = Ray {
+        origin: Vec3::new(0.0, 2.0, 0.0),
+        direction: Vec3::new(0.0, 1.0, 0.0),
+    };
+    let lamp = room.world.hit(&ray, 0.001, f64::MAX).unwrap();
+    assert!((lamp.p.y - 3.0).abs() < 1e-6);
+    match lamp.material {
+        Material::Emissive { radiance } => assert_close(radiance, Vec3::new(4.0, 3.6, 3.2)),
+        _ => panic!("the lamp should be emissive"),
+    }
+}
+
+#[test]
+fn room_camera_is_placed_by_its_parent_node() {
+    let camera = gltf_import::load(ROOM, None).unwrap().camera.unwrap();
+    assert_close(camera.look_from, Vec3::new(0.0, 2.0, 6.0));
+    // Tilted 15 degrees down.
+    let (sin, cos) = 15f64.to_radians().sin_cos();
+    assert_close(
+        (camera.look_at - camera.look_from).unit(),
+        Vec3::new(0.0, -sin, -cos),
+    );
+    assert!((camera.vfov - 40.0).abs() < 1e-4, "{}", camera.vfov);
+    assert_eq!(camera.aspect_ratio, Some(2.0));
+}
+
+#[test]
+fn room_lights_are_placed_by_their_nodes() {
+    let lights = gltf_import::load(ROOM, None).unwrap().lights;
+    assert_eq!(lights.len(), 2);
+    let (sin, cos) = 60f64.to_radians().sin_cos();
+    match lights[0] {
+        Light::Directional {
+            direction,
+            irradiance,
+        } => {
+            assert_close(direction.unit(), Vec3::new(0.0, -sin, -cos));
+            assert_close(irradiance, Vec3::new(1.0, 0.95, 0.9));
+        }
+        _ => panic!("the sun should be a directional light"),
+    }
+    match lights[1] {
+        Light::Point {
+            position,
+            intensity,
+        } => {
+            assert_close(position, Vec3::new(3.0, 3.0, 3.0));
+            assert_close(intensity, Vec3::new_uniform(4.0));
+        }
+        _ => panic!("the fill light should be a point light"),
+    }
+}
+
+#[test]
+fn gltf_camera_matches_the_image_not_the_file() {
+    let scene = format!(
+        "[renderer]\nsamples = 1\nwidth = 400\nheight = 300\nfilename = \"room.png\"\n\n\
+         [[objects]]\ntype = \"gltf\"\npath = {:?}\n",
+        ROOM
+    );
+    let camera = scene_file::parse(&scene).unwrap().renderer.camera;
+    let aspect_ratio = camera.horizontal.length() / camera.vertical.length();
+    assert!((aspect_ratio - 4.0 / 3.0).abs() < 1e-9, "{}", aspect_ratio);
+}
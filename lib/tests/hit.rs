@@ -0,0 +1,79 @@
+use raytracer::hittable::{Hittable, Sphere, Triangle};
+use raytracer::material::Material;
+use raytracer::ray::Ray;
+use raytracer::vector::Vec3;
+
+const MATERIAL: Material = Material::Diffuse {
+    albedo: Vec3 {
+        x: 0.5,
+        y: 0.5,
+        z: 0.5,
+    },
+};
+
+fn assert_close(actual: Vec3, expected: Vec3) {
+    assert!(
+        (actual - expected).length() < 1e-9,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+fn ray(origin: Vec3, direction: Vec3) -> Ray {
+    Ray { origin, direction }
+}
+
+#[test]
+fn triangle_normal_faces_the_ray_from_either_side() {
+    // Counter-clockwise seen from +z, so the outward normal is +z.
+    let triangle = Hittable::Triangle(Triangle {
+        vertices: [
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ],
+        material: MATERIAL,
+    });
+
+    let front = triangle
+        .hit(
+            &ray(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0)),
+            0.001,
+            f64::MAX,
+        )
+        .expect("ray from the front should hit");
+    assert!(front.front_face);
+    assert_close(front.normal, Vec3::new(0.0, 0.0, 1.0));
+
+    let back = triangle
+        .hit(
+            &ray(Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0)),
+            0.001,
+            f64::MAX,
+        )
+        .expect("ray from behind should hit");
+    assert!(!back.front_face);
+    assert_close(back.normal, Vec3::new(0.0, 0.0, -1.0));
+}
+
+#[test]
+fn sphere_normal_faces_the_ray_from_inside() {
+    let sphere = Hittable::Sphere(Sphere {
+        center: Vec3::zero(),
+        radius: 1.0,
+        material: MATERIAL,
+    });
+    let hit = sphere
+        .hit(
+            &ray(Vec3::zero(), Vec3::new(1.0, 0.0, 0.0)),
+            0.001,
+            f64::MAX,
+        )
+        .expect("ray from the center should hit");
+    assert!(!hit.front_face);
+    assert_close(hit.normal, Vec3::new(-1.0, 0.0, 0.0));
+    // Halfway around from -x, and halfway up.
+    let (u, v) = hit.uv();
+    assert!((u - 0.5).abs() < 1e-9 && (v - 0.5).abs() < 1e-9);
+}
@@ -0,0 +1,125 @@
+use raytracer::integrator::AnyIntegrator;
+use raytracer::scene_file::{self, SceneFileError};
+
+const RENDERER: &str = r#"
+[renderer]
+samples = 4
+width = 40
+height = 20
+filename = "test.png"
+"#;
+
+const CAMERA: &str = r#"
+[camera]
+look_from = [0.0, 0.0, 0.0]
+look_at = [0.0, 0.0, -1.0]
+vfov = 90.0
+"#;
+
+const SPHERES: &str = r#"
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, -1.0]
+radius = 0.5
+material = { type = "diffuse", albedo = [0.8, 0.3, 0.8] }
+
+[[objects]]
+type = "sphere"
+center = [1.0, 0.0, -1.0]
+radius = 0.5
+material = { type = "metal", albedo = [0.8, 0.8, 0.8], fuzz = 0.3 }
+"#;
+
+/// Parses a scene file which should be rejected, and returns the error message.
+fn error(contents: &str) -> String {
+    match scene_file::parse(contents) {
+        Ok(_) => panic!("scene file should have been rejected:\n{}", contents),
+        Err(err) => err.to_string(),
+    }
+}
+
+#[test]
+fn valid_scene() {
+    let setup = scene_file::parse(&format!("{}{}{}", RENDERER, CAMERA, SPHERES)).unwrap();
+    assert_eq!((setup.width, setup.height), (40, 20));
+    assert_eq!(setup.renderer.samples, 4);
+    assert_eq!(setup.renderer.filename, "test.png");
+    assert_eq!(setup.renderer.output_dir, "output");
+    assert!(setup.scene.world.bounding_box().is_some());
+    match setup.integrator {
+        AnyIntegrator::Path(path_tracer) => assert_eq!(path_tracer.path.max_depth, 50),
+        _ => panic!("the path tracer should be the default"),
+    }
+}
+
+#[test]
+fn errors_point_at_the_bad_field() {
+    let bad_fuzz = SPHERES.replace("fuzz = 0.3", "fuzz = 1.5");
+    assert_eq!(
+        error(&format!("{}{}{}", RENDERER, CAMERA, bad_fuzz)),
+        "invalid `objects[1].material.fuzz`: must be between 0 and 1"
+    );
+
+    let no_samples = RENDERER.replace("samples = 4", "samples = 0");
+    assert_eq!(
+        error(&format!("{}{}{}", no_samples, CAMERA, SPHERES)),
+        "invalid `renderer.samples`: must be at least 1"
+    );
+
+    let huge_save_interval = format!(
+        "{}progressive = {{ samples_per_pass = 1, save_interval_secs = 1e30 }}\n{}",
+        RENDERER, CAMERA
+    );
+    assert_eq!(
+        error(&huge_save_interval),
+        "invalid `renderer.progressive.save_interval_secs`: must be a positive number of \
+         seconds, and not too large"
+    );
+}
+
+#[test]
+fn unknown_fields_are_rejected() {
+    let typo = RENDERER.replace("samples = 4", "samples = 4\nsampels = 4");
+    let err = scene_file::parse(&format!("{}{}", typo, CAMERA))
+        .err()
+        .unwrap();
+    assert!(matches!(err, SceneFileError::Parse(_)));
+    assert!(
+        err.to_string().contains("unknown field `sampels`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn old_path_settings_still_work_without_an_integrator_section() {
+    let old = format!(
+        "{}max_depth = 5\nrussian_roulette = {{ start_depth = 2 }}\n{}",
+        RENDERER, CAMERA
+    );
+    match scene_file::parse(&old).unwrap().integrator {
+        AnyIntegrator::Path(path_tracer) => {
+            assert_eq!(path_tracer.path.max_depth, 5);
+            assert_eq!(path_tracer.path.russian_roulette.unwrap().start_depth, 2);
+        }
+        _ => panic!("the old settings are for the path tracer"),
+    }
+
+    let both = format!(
+        "{}max_depth = 5\n{}\n[integrator]\ntype = \"path\"\n",
+        RENDERER, CAMERA
+    );
+    assert_eq!(
+        error(&both),
+        "invalid `renderer`: max_depth and russian_roulette go in the [integrator] section when \
+         there is one"
+    );
+}
+
+#[test]
+fn camera_is_needed_without_a_gltf_camera() {
+    assert_eq!(
+        error(&format!("{}{}", RENDERER, SPHERES)),
+        "invalid `camera`: missing, and no glTF object has a camera"
+    );
+}
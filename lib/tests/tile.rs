@@ -0,0 +1,57 @@
+use raytracer::tile::{tiles, Tile, TileOrder};
+
+const ORDERS: [TileOrder; 3] = [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert];
+
+#[test]
+fn every_pixel_is_in_exactly_one_tile() {
+    // Sizes which divide evenly, which don't, images smaller than a tile, and 1 pixel wide or tall.
+    let cases = [
+        (64, 32, 16),
+        (100, 37, 16),
+        (7, 5, 32),
+        (1, 50, 8),
+        (50, 1, 8),
+        (1, 1, 1),
+        (33, 17, 1),
+    ];
+    for order in ORDERS {
+        for (width, height, size) in cases {
+            let mut covered = vec![0; width * height];
+            for tile in tiles(width, height, size, order) {
+                assert!(tile.size() > 0, "{:?} is empty", tile);
+                assert!(tile.x + tile.width <= width && tile.y + tile.height <= height);
+                for y in tile.y..tile.y + tile.height {
+                    for x in tile.x..tile.x + tile.width {
+                        covered[y * width + x] += 1;
+                    }
+                }
+            }
+            assert!(
+                covered.iter().all(|&n| n == 1),
+                "{:?} tiles of {} pixels don't cover a {}x{} image exactly once",
+                order,
+                size,
+                width,
+                height
+            );
+        }
+    }
+}
+
+#[test]
+fn hilbert_order_moves_to_a_neighbouring_tile_each_time() {
+    let next_to = |a: &Tile, b: &Tile| {
+        let (dx, dy) = (a.x.abs_diff(b.x), a.y.abs_diff(b.y));
+        (dx, dy) == (4, 0) || (dx, dy) == (0, 4)
+    };
+    let order = tiles(32, 32, 4, TileOrder::Hilbert);
+    for pair in order.windows(2) {
+        assert!(next_to(&pair[0], &pair[1]), "{:?}", pair);
+    }
+}
+
+#[test]
+fn spiral_order_starts_in_the_middle() {
+    let first = tiles(90, 90, 10, TileOrder::Spiral)[0];
+    assert_eq!((first.x, first.y), (40, 40));
+}